@@ -1,42 +1,212 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::BufRead;
 use std::num::ParseIntError;
-use std::str::FromStr;
+
+use anyhow::Result;
 
 struct Elf {
     calories: Vec<u32>,
 }
 
-impl FromStr for Elf {
-    type Err = ParseIntError;
+impl Elf {
+    fn total(&self) -> u32 {
+        self.calories.iter().sum()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct ParseElfError {
+    /// The elf the bad line belongs to, counting from 0.
+    elf: usize,
+    /// The bad line's number in the log, counting from 1.
+    line: usize,
+    source: ParseIntError,
+}
+
+impl std::fmt::Display for ParseElfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid calories for elf {} on line {}: {}",
+            self.elf, self.line, self.source
+        )
+    }
+}
+
+impl std::error::Error for ParseElfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Lazily parse elves from the lines of a calorie log, one blank-line
+/// separated block at a time.
+///
+/// Surrounding whitespace (including the '\r' of a CRLF line ending) is
+/// ignored, and any number of blank lines may separate two elves.
+struct Elves<I> {
+    lines: I,
+    /// Lines read so far.
+    line: usize,
+    /// Elves read so far.
+    elf: usize,
+}
+
+impl<I> Elves<I> {
+    fn new(lines: I) -> Self {
+        Elves {
+            lines,
+            line: 0,
+            elf: 0,
+        }
+    }
+}
+
+impl<I, S> Iterator for Elves<I>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    type Item = Result<Elf, ParseElfError>;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let calories: Result<Vec<u32>, _> = s.split('\n').map(|s| s.parse()).collect();
-        Ok(Elf {
-            calories: calories?,
-        })
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut calories = Vec::new();
+        for line in self.lines.by_ref() {
+            self.line += 1;
+            let line = line.as_ref().trim();
+            match (line.is_empty(), calories.is_empty()) {
+                (true, true) => continue,
+                (true, false) => break,
+                _ => {}
+            }
+            match line.parse() {
+                Ok(c) => calories.push(c),
+                Err(source) => {
+                    return Some(Err(ParseElfError {
+                        elf: self.elf,
+                        line: self.line,
+                        source,
+                    }))
+                }
+            }
+        }
+        if calories.is_empty() {
+            return None;
+        }
+        self.elf += 1;
+        Some(Ok(Elf { calories }))
     }
 }
 
-pub fn part1(input: String) {
-    let elves: Result<Vec<Elf>, _> = input.split("\n\n").map(|s| s.parse()).collect();
-    let max = elves.unwrap().iter().fold(0u32, |max, elf| {
-        let total: u32 = elf.calories.iter().sum();
-        if total > max {
-            total
-        } else {
-            max
+/// The `k` largest values seen so far, holding no more than `k` at once.
+struct TopK<T> {
+    k: usize,
+    // A min-heap, so the smallest of the current top k is evicted first.
+    heap: BinaryHeap<Reverse<T>>,
+}
+
+impl<T: Ord> TopK<T> {
+    fn new(k: usize) -> Self {
+        TopK {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
         }
-    });
+    }
+
+    fn push(&mut self, value: T) {
+        self.heap.push(Reverse(value));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    /// The values kept, largest first.
+    fn into_sorted_vec(self) -> Vec<T> {
+        // Ascending order of `Reverse` is descending order of the values.
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(value)| value)
+            .collect()
+    }
+}
+
+/// The totals of the `k` elves carrying the most calories, largest first.
+fn top_k<S: AsRef<str>>(lines: impl Iterator<Item = S>, k: usize) -> Result<Vec<u32>> {
+    let mut top = TopK::new(k);
+    for elf in Elves::new(lines) {
+        top.push(elf?.total());
+    }
+    Ok(top.into_sorted_vec())
+}
+
+/// [`top_k`] over a calorie log read one line at a time.
+fn read_top_k(input: impl BufRead, k: usize) -> Result<Vec<u32>> {
+    let mut error = None;
+    let lines = input
+        .lines()
+        .map_while(|line| line.map_err(|e| error = Some(e)).ok());
+    let top = top_k(lines, k);
+    match error {
+        Some(e) => Err(e.into()),
+        None => top,
+    }
+}
+
+pub fn part1(input: impl BufRead) -> Result<u32> {
+    let max = read_top_k(input, 1)?.first().copied().unwrap_or(0);
     println!("day01 part1: calories={}", max);
+    Ok(max)
 }
 
-pub fn part2(input: String) {
-    let elves: Result<Vec<Elf>, _> = input.split("\n\n").map(|s| s.parse()).collect();
-    let mut totals: Vec<u32> = elves
-        .unwrap()
-        .iter()
-        .map(|elf| elf.calories.iter().sum())
-        .collect();
-    totals.sort_by(|a, b| b.cmp(a));
-    let sum_of_top_3: u32 = totals.iter().take(3).sum();
-    println!("day01 part2: calories={}", sum_of_top_3);
+/// Sum the calories carried by the top `k` elves (3 for the puzzle).
+pub fn part2(input: impl BufRead, k: usize) -> Result<u32> {
+    let sum_of_top_k: u32 = read_top_k(input, k)?.iter().sum();
+    println!("day01 part2: calories={}", sum_of_top_k);
+    Ok(sum_of_top_k)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000";
+
+    #[test]
+    fn it_works() {
+        assert_eq!(part1(EXAMPLE.as_bytes()).unwrap(), 24000);
+        assert_eq!(part2(EXAMPLE.as_bytes(), 3).unwrap(), 45000);
+    }
+
+    #[test]
+    fn top_k_elves() {
+        assert_eq!(top_k(EXAMPLE.lines(), 2).unwrap(), vec![24000, 11000]);
+        assert_eq!(top_k(EXAMPLE.lines(), 0).unwrap(), vec![]);
+        assert_eq!(top_k(EXAMPLE.lines(), 10).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn tolerates_whitespace_and_crlf() {
+        let input = "\r\n1000\r\n2000 \r\n\r\n\r\n  3000\r\n\r\n";
+        assert_eq!(top_k(input.lines(), 3).unwrap(), vec![3000, 3000]);
+        assert_eq!(part1(format!("{}\n", EXAMPLE).as_bytes()).unwrap(), 24000);
+    }
+
+    #[test]
+    fn reports_bad_lines() {
+        let input = "1000\n\n2000\nabc\n";
+        let err = Elves::new(input.lines())
+            .collect::<Result<Vec<_>, _>>()
+            .err()
+            .unwrap();
+        assert_eq!((err.elf, err.line), (1, 4));
+        assert_eq!(
+            err.to_string(),
+            "invalid calories for elf 1 on line 4: invalid digit found in string"
+        );
+        assert!(part1(input.as_bytes()).is_err());
+        // Read errors (here, invalid UTF-8) aren't mistaken for the end of the log.
+        assert!(part1(&b"1000\n\n\xff\n"[..]).is_err());
+    }
 }
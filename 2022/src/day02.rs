@@ -1,28 +1,10 @@
 use anyhow::Result;
 use std::str::FromStr;
 
-enum Code {
-    A,
-    B,
-    C,
-}
-
-#[derive(Debug)]
-struct ParseCodeError;
-
-impl FromStr for Code {
-    type Err = ParseCodeError;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "A" | "X" => Ok(Code::A),
-            "B" | "Y" => Ok(Code::B),
-            "C" | "Z" => Ok(Code::C),
-            _ => Err(ParseCodeError),
-        }
-    }
-}
-
-struct Round(Code, Code);
+/// The two letters of a line in the strategy guide, before they're given
+/// any meaning.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Round(char, char);
 
 #[derive(Debug)]
 struct ParseRoundError;
@@ -31,117 +13,610 @@ impl FromStr for Round {
     type Err = ParseRoundError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let results = s
-            .split_once(' ')
-            .map(|(a, b)| (a.parse::<Code>(), b.parse::<Code>()));
+        let letter = |s: &str| {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => None,
+            }
+        };
+        let results = s.split_once(' ').map(|(a, b)| (letter(a), letter(b)));
         match results {
-            Some((Ok(a), Ok(b))) => Ok(Self(a, b)),
+            Some((Some(a), Some(b))) => Ok(Self(a, b)),
             _ => Err(ParseRoundError),
         }
     }
 }
 
-#[derive(Clone)]
-enum Move {
-    Rock,
-    Paper,
-    Scissors,
+/// A move, by its position in a game's cycle of moves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Move(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Lose,
+    Tie,
+    Win,
+}
+
+impl Outcome {
+    fn all() -> [Outcome; 3] {
+        [Outcome::Lose, Outcome::Tie, Outcome::Win]
+    }
+}
+
+/// The rules and scoring of a rock-paper-scissors style game.
+#[derive(Debug, Clone)]
+struct Game {
+    names: Vec<String>,
+    /// `beats[a][b]` is whether move `a` defeats move `b`.
+    beats: Vec<Vec<bool>>,
+    /// The score for playing each move.
+    shape_scores: Vec<i32>,
+    /// The score for losing, tying and winning a round.
+    outcome_scores: [i32; 3],
 }
 
-impl From<Code> for Move {
-    fn from(code: Code) -> Self {
-        match code {
-            Code::A => Move::Rock,
-            Code::B => Move::Paper,
-            Code::C => Move::Scissors,
+impl Game {
+    /// A game of an odd number of moves, where each move defeats the half of
+    /// the other moves which come before it in the cycle.
+    ///
+    /// Moves score 1, 2, 3, ... in order, and rounds score 0, 3 or 6.
+    fn cyclic(names: &[&str]) -> Result<Self> {
+        let n = names.len();
+        anyhow::ensure!(n % 2 == 1, "a cyclic game needs an odd number of moves");
+        for (i, name) in names.iter().enumerate() {
+            anyhow::ensure!(!name.is_empty(), "moves need a name");
+            anyhow::ensure!(
+                !names[..i].iter().any(|n| n.eq_ignore_ascii_case(name)),
+                "move {:?} appears twice",
+                name
+            );
+        }
+        Ok(Game {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            beats: (0..n)
+                .map(|a| {
+                    (0..n)
+                        .map(|b| (1..=n / 2).contains(&((a + n - b) % n)))
+                        .collect()
+                })
+                .collect(),
+            shape_scores: (1..=n as i32).collect(),
+            outcome_scores: [0, 3, 6],
+        })
+    }
+
+    fn rock_paper_scissors() -> Self {
+        Game::cyclic(&["Rock", "Paper", "Scissors"]).unwrap()
+    }
+
+    fn rock_paper_scissors_lizard_spock() -> Self {
+        Game::cyclic(&["Rock", "Spock", "Paper", "Lizard", "Scissors"]).unwrap()
+    }
+
+    /// The move with the given name, ignoring case.
+    fn find(&self, name: &str) -> Option<Move> {
+        self.names
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name))
+            .map(Move)
+    }
+
+    fn moves(&self) -> impl Iterator<Item = Move> {
+        (0..self.names.len()).map(Move)
+    }
+
+    /// The outcome of a round for whoever played `me`.
+    fn outcome(&self, them: Move, me: Move) -> Outcome {
+        if self.beats[me.0][them.0] {
+            Outcome::Win
+        } else if self.beats[them.0][me.0] {
+            Outcome::Lose
+        } else {
+            Outcome::Tie
         }
     }
+
+    fn score(&self, them: Move, me: Move) -> i32 {
+        self.shape_scores[me.0] + self.outcome_scores[self.outcome(them, me) as usize]
+    }
+
+    /// The move to play against `them` to get the outcome, picking the
+    /// highest scoring if several would.
+    fn should_play(&self, them: Move, outcome: Outcome) -> Option<Move> {
+        self.moves()
+            .filter(|&me| self.outcome(them, me) == outcome)
+            .max_by_key(|&me| (self.score(them, me), std::cmp::Reverse(me.0)))
+    }
 }
 
-impl Move {
-    fn defeats(&self, other: &Move) -> bool {
-        matches!(
-            (&self, &other),
-            (Move::Rock, Move::Scissors)
-                | (Move::Paper, Move::Rock)
-                | (Move::Scissors, Move::Paper)
-        )
+impl FromStr for Game {
+    type Err = anyhow::Error;
+
+    /// A game by name, or its moves in cycle order separated by commas, e.g
+    /// "Rock,Spock,Paper,Lizard,Scissors".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rock-paper-scissors" => Ok(Game::rock_paper_scissors()),
+            "rock-paper-scissors-lizard-spock" => Ok(Game::rock_paper_scissors_lizard_spock()),
+            _ => Game::cyclic(&s.split(',').map(str::trim).collect::<Vec<_>>()),
+        }
     }
+}
 
-    fn should_play(&self, outcome: &Outcome) -> Move {
-        match outcome {
-            Outcome::Win => match self {
-                Move::Rock => Move::Paper,
-                Move::Paper => Move::Scissors,
-                Move::Scissors => Move::Rock,
-            },
-            Outcome::Lose => match self {
-                Move::Rock => Move::Scissors,
-                Move::Paper => Move::Rock,
-                Move::Scissors => Move::Paper,
+/// What the letters in the second column of the guide ask us to do.
+#[derive(Debug, Clone, PartialEq)]
+enum Response {
+    /// Play a move.
+    Move(Vec<(char, Move)>),
+    /// Play whichever move gets an outcome.
+    Outcome(Vec<(char, Outcome)>),
+}
+
+/// One interpretation of the strategy guide's letters.
+///
+/// Written (and parsed) as the meaning of each column's letters, e.g
+/// "A=Rock,B=Paper,C=Scissors X=Lose,Y=Tie,Z=Win".
+#[derive(Debug, Clone, PartialEq)]
+struct Decoder {
+    /// Their move for each letter of the first column.
+    them: Vec<(char, Move)>,
+    me: Response,
+}
+
+impl Decoder {
+    fn parse(spec: &str, game: &Game) -> Result<Self> {
+        let columns: Vec<&str> = spec.split_whitespace().collect();
+        let [them, me] = columns[..] else {
+            anyhow::bail!("Invalid guide {:?}, expected two columns", spec);
+        };
+        let entries = |column: &'static str, s: &str| -> Result<Vec<(char, String)>> {
+            let mut entries: Vec<(char, String)> = Vec::new();
+            for entry in s.split(',') {
+                let (letter, name) = match entry.split_once('=') {
+                    Some((letter, name)) if letter.chars().count() == 1 => {
+                        (letter.chars().next().unwrap(), name.to_string())
+                    }
+                    _ => anyhow::bail!("Invalid {} entry {:?}", column, entry),
+                };
+                anyhow::ensure!(
+                    entries.iter().all(|(l, _)| *l != letter),
+                    "'{}' is given twice in the {}",
+                    letter,
+                    column
+                );
+                entries.push((letter, name));
+            }
+            Ok(entries)
+        };
+        let as_moves = |entries: &[(char, String)]| -> Option<Vec<(char, Move)>> {
+            entries
+                .iter()
+                .map(|(letter, name)| Some((*letter, game.find(name)?)))
+                .collect()
+        };
+        let as_outcomes = |entries: &[(char, String)]| -> Option<Vec<(char, Outcome)>> {
+            entries
+                .iter()
+                .map(|(letter, name)| {
+                    let outcome = Outcome::all()
+                        .into_iter()
+                        .find(|o| format!("{:?}", o).eq_ignore_ascii_case(name))?;
+                    Some((*letter, outcome))
+                })
+                .collect()
+        };
+        let them = entries("first column", them)?;
+        let me = entries("second column", me)?;
+        Ok(Decoder {
+            them: as_moves(&them)
+                .ok_or_else(|| anyhow::anyhow!("Unknown move in first column {:?}", them))?,
+            me: match (as_moves(&me), as_outcomes(&me)) {
+                (Some(moves), _) => Response::Move(moves),
+                (_, Some(outcomes)) => Response::Outcome(outcomes),
+                _ => anyhow::bail!("Second column {:?} must be all moves or all outcomes", me),
             },
-            Outcome::Tie => self.clone(),
-        }
+        })
+    }
+
+    /// Write the decoder in the form accepted by [`Decoder::parse`].
+    fn describe(&self, game: &Game) -> String {
+        let them: Vec<_> = self
+            .them
+            .iter()
+            .map(|(c, m)| format!("{}={}", c, game.names[m.0]))
+            .collect();
+        let me: Vec<_> = match &self.me {
+            Response::Move(moves) => moves
+                .iter()
+                .map(|(c, m)| format!("{}={}", c, game.names[m.0]))
+                .collect(),
+            Response::Outcome(outcomes) => outcomes
+                .iter()
+                .map(|(c, o)| format!("{}={:?}", c, o))
+                .collect(),
+        };
+        format!("{} {}", them.join(","), me.join(","))
+    }
+
+    /// Their move and ours for a round of the guide.
+    fn decode(&self, game: &Game, Round(a, b): Round) -> Result<(Move, Move)> {
+        let them = self
+            .them
+            .iter()
+            .find(|(letter, _)| *letter == a)
+            .map(|(_, m)| *m)
+            .ok_or_else(|| anyhow::anyhow!("No meaning for '{}' in the first column", a))?;
+        Ok((them, self.respond(game, them, b)?))
+    }
+
+    /// Our move against `them` for a letter of the second column.
+    fn respond(&self, game: &Game, them: Move, b: char) -> Result<Move> {
+        let me = match &self.me {
+            Response::Move(moves) => moves
+                .iter()
+                .find(|(letter, _)| *letter == b)
+                .map(|(_, m)| *m),
+            Response::Outcome(outcomes) => outcomes
+                .iter()
+                .find(|(letter, _)| *letter == b)
+                .and_then(|(_, outcome)| game.should_play(them, *outcome)),
+        };
+        me.ok_or_else(|| anyhow::anyhow!("No meaning for '{}' in the second column", b))
+    }
+
+    /// The total score from following the guide.
+    fn total(&self, game: &Game, rounds: &[Round]) -> Result<i32> {
+        rounds
+            .iter()
+            .map(|round| {
+                let (them, me) = self.decode(game, *round)?;
+                Ok(game.score(them, me))
+            })
+            .sum()
+    }
+
+    /// Every reading of the guide where each letter in a column means a
+    /// different move (or, for the second column, a different outcome).
+    ///
+    /// Fails if a column has more letters than there are meanings for them.
+    fn all(game: &Game, rounds: &[Round]) -> Result<Vec<Decoder>> {
+        let letters = |column: fn(&Round) -> char| {
+            let mut letters: Vec<char> = rounds.iter().map(column).collect();
+            letters.sort_unstable();
+            letters.dedup();
+            letters
+        };
+        let first = letters(|r| r.0);
+        let second = letters(|r| r.1);
+        let moves: Vec<Move> = game.moves().collect();
+        anyhow::ensure!(
+            first.len() <= moves.len(),
+            "The first column has {} letters but there are only {} moves",
+            first.len(),
+            moves.len()
+        );
+        anyhow::ensure!(
+            second.len() <= moves.len().max(Outcome::all().len()),
+            "The second column has {} letters but there are only {} moves or {} outcomes",
+            second.len(),
+            moves.len(),
+            Outcome::all().len()
+        );
+        let mut responses: Vec<Response> = arrangements(&moves, second.len())
+            .into_iter()
+            .map(|moves| Response::Move(second.iter().copied().zip(moves).collect()))
+            .collect();
+        responses.extend(
+            arrangements(&Outcome::all(), second.len())
+                .into_iter()
+                .map(|outcomes| Response::Outcome(second.iter().copied().zip(outcomes).collect())),
+        );
+        Ok(arrangements(&moves, first.len())
+            .into_iter()
+            .flat_map(|them| {
+                let them: Vec<_> = first.iter().copied().zip(them).collect();
+                responses.iter().map(move |me| Decoder {
+                    them: them.clone(),
+                    me: me.clone(),
+                })
+            })
+            .collect())
     }
 }
 
-enum Outcome {
-    Win,
-    Lose,
-    Tie,
+/// A round as it was played and scored.
+#[derive(Debug, Clone, PartialEq)]
+struct Play {
+    them: Move,
+    me: Move,
+    outcome: Outcome,
+    shape_score: i32,
+    outcome_score: i32,
+    /// The running total, including this round.
+    total: i32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Stats {
+    wins: usize,
+    losses: usize,
+    ties: usize,
 }
 
-impl From<Code> for Outcome {
-    fn from(code: Code) -> Self {
-        match code {
-            Code::A => Outcome::Lose,
-            Code::B => Outcome::Tie,
-            Code::C => Outcome::Win,
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "wins={} losses={} ties={}",
+            self.wins, self.losses, self.ties
+        )
+    }
+}
+
+/// Every round of a game, in order.
+#[derive(Debug, Clone, PartialEq)]
+struct Tournament {
+    plays: Vec<Play>,
+}
+
+impl Tournament {
+    fn play(game: &Game, moves: impl IntoIterator<Item = (Move, Move)>) -> Self {
+        let mut total = 0;
+        let plays = moves
+            .into_iter()
+            .map(|(them, me)| {
+                let outcome = game.outcome(them, me);
+                let shape_score = game.shape_scores[me.0];
+                let outcome_score = game.outcome_scores[outcome as usize];
+                total += shape_score + outcome_score;
+                Play {
+                    them,
+                    me,
+                    outcome,
+                    shape_score,
+                    outcome_score,
+                    total,
+                }
+            })
+            .collect();
+        Tournament { plays }
+    }
+
+    /// Play the rounds as the guide reads them.
+    fn follow(game: &Game, decoder: &Decoder, rounds: &[Round]) -> Result<Self> {
+        let moves = rounds
+            .iter()
+            .map(|round| decoder.decode(game, *round))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Tournament::play(game, moves))
+    }
+
+    /// Play the guide's responses against an opponent who ignores its first
+    /// column.
+    fn simulate(
+        game: &Game,
+        decoder: &Decoder,
+        rounds: &[Round],
+        opponent: &mut Opponent,
+    ) -> Result<Self> {
+        let moves = rounds
+            .iter()
+            .map(|round| {
+                let them = opponent.next_move(game);
+                Ok((them, decoder.respond(game, them, round.1)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Tournament::play(game, moves))
+    }
+
+    fn total(&self) -> i32 {
+        self.plays.last().map_or(0, |play| play.total)
+    }
+
+    fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        for play in &self.plays {
+            match play.outcome {
+                Outcome::Win => stats.wins += 1,
+                Outcome::Lose => stats.losses += 1,
+                Outcome::Tie => stats.ties += 1,
+            }
         }
+        stats
+    }
+
+    fn to_csv(&self, game: &Game) -> String {
+        let mut csv = String::from("round,them,me,outcome,shape_score,outcome_score,total\n");
+        for (i, play) in self.plays.iter().enumerate() {
+            csv += &format!(
+                "{},{},{},{:?},{},{},{}\n",
+                i + 1,
+                game.names[play.them.0],
+                game.names[play.me.0],
+                play.outcome,
+                play.shape_score,
+                play.outcome_score,
+                play.total
+            );
+        }
+        csv
+    }
+}
+
+/// A simple xorshift generator, so simulations can be repeated from a seed.
+#[derive(Debug, Clone)]
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // An all-zero state would only ever produce zeros.
+        XorShift(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
     }
 }
 
-fn score(them: &Move, me: &Move) -> i32 {
-    let shape_score = match me {
-        Move::Rock => 1,
-        Move::Paper => 2,
-        Move::Scissors => 3,
-    };
+/// How a simulated opponent picks their moves.
+#[derive(Debug, Clone)]
+enum Opponent {
+    /// Always play the same move.
+    Fixed(Move),
+    /// Play uniformly at random.
+    Random(XorShift),
+}
+
+impl Opponent {
+    /// Parse a move name (e.g "Rock") or "random:SEED".
+    fn parse(spec: &str, game: &Game) -> Result<Self> {
+        if let Some(seed) = spec.strip_prefix("random:") {
+            return Ok(Opponent::Random(XorShift::new(seed.parse()?)));
+        }
+        game.find(spec)
+            .map(Opponent::Fixed)
+            .ok_or_else(|| anyhow::anyhow!("Unknown opponent {:?}", spec))
+    }
 
-    let outcome_score = them
-        .defeats(me)
-        .then_some(0)
-        .or_else(|| me.defeats(them).then_some(6))
-        .unwrap_or(3);
+    fn next_move(&mut self, game: &Game) -> Move {
+        match self {
+            Opponent::Fixed(m) => *m,
+            Opponent::Random(rng) => Move((rng.next_u64() % game.names.len() as u64) as usize),
+        }
+    }
+}
 
-    shape_score + outcome_score
+/// Every ordered selection of `k` distinct items.
+fn arrangements<T: Copy>(items: &[T], k: usize) -> Vec<Vec<T>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+    (0..items.len())
+        .flat_map(|i| {
+            let mut rest = items.to_vec();
+            let first = rest.remove(i);
+            arrangements(&rest, k - 1).into_iter().map(move |mut tail| {
+                tail.insert(0, first);
+                tail
+            })
+        })
+        .collect()
 }
 
-pub fn part1(input: String) -> Result<i32> {
-    let total: i32 = input
+fn parse_rounds(input: &str) -> Result<Vec<Round>> {
+    input
         .lines()
         .map(|line| {
-            let Round(a, b): Round = line.parse().unwrap();
-            score(&Move::from(a), &Move::from(b))
+            line.parse()
+                .map_err(|_| anyhow::anyhow!("Invalid round {:?}", line))
         })
-        .sum();
+        .collect()
+}
+
+/// The guide's meaning according to the elf's full explanation.
+/// The game the puzzle is played with, in the form accepted by `--game`.
+pub const GAME: &str = "rock-paper-scissors";
+
+pub const GUIDE: &str = "A=Rock,B=Paper,C=Scissors X=Lose,Y=Tie,Z=Win";
+
+pub fn part1(input: String, game: &str) -> Result<i32> {
+    let game: Game = game.parse()?;
+    let decoder = Decoder::parse("A=Rock,B=Paper,C=Scissors X=Rock,Y=Paper,Z=Scissors", &game)?;
+    let total = decoder.total(&game, &parse_rounds(&input)?)?;
     println!("day02 part1: score={}", total);
     Ok(total)
 }
 
-pub fn part2(input: String) -> Result<i32> {
-    let total: i32 = input
-        .lines()
-        .map(|line| {
-            let Round(a, b): Round = line.parse().unwrap();
-            let move_them = Move::from(a);
-            score(&move_them, &move_them.should_play(&Outcome::from(b)))
-        })
-        .sum();
+pub fn part2(input: String, game: &str) -> Result<i32> {
+    let game: Game = game.parse()?;
+    let decoder = Decoder::parse(GUIDE, &game)?;
+    let total = decoder.total(&game, &parse_rounds(&input)?)?;
     println!("day02 part2: score={}", total);
     Ok(total)
 }
 
+/// Score the guide with a user-supplied meaning for its letters.
+pub fn decode(input: String, game: &str, spec: &str) -> Result<i32> {
+    let game: Game = game.parse()?;
+    let total = Decoder::parse(spec, &game)?.total(&game, &parse_rounds(&input)?)?;
+    println!("day02 guide: score={}", total);
+    Ok(total)
+}
+
+/// Play the guide round by round, printing each round and optionally
+/// writing them to a CSV file.
+pub fn report(input: String, game: &str, spec: &str, csv: Option<String>) -> Result<i32> {
+    let game: Game = game.parse()?;
+    let decoder = Decoder::parse(spec, &game)?;
+    let tournament = Tournament::follow(&game, &decoder, &parse_rounds(&input)?)?;
+    for (i, play) in tournament.plays.iter().enumerate() {
+        println!(
+            "day02 round {}: them={} me={} outcome={:?} shape={} outcome={} total={}",
+            i + 1,
+            game.names[play.them.0],
+            game.names[play.me.0],
+            play.outcome,
+            play.shape_score,
+            play.outcome_score,
+            play.total
+        );
+    }
+    println!(
+        "day02 report: {} score={}",
+        tournament.stats(),
+        tournament.total()
+    );
+    if let Some(path) = csv {
+        std::fs::write(path, tournament.to_csv(&game))?;
+    }
+    Ok(tournament.total())
+}
+
+/// Play the guide's responses against a simulated opponent, either a fixed
+/// move (e.g "Rock") or "random:SEED".
+pub fn simulate(input: String, game: &str, spec: &str, opponent: &str) -> Result<i32> {
+    let game: Game = game.parse()?;
+    let decoder = Decoder::parse(spec, &game)?;
+    let mut opponent = Opponent::parse(opponent, &game)?;
+    let tournament = Tournament::simulate(&game, &decoder, &parse_rounds(&input)?, &mut opponent)?;
+    println!(
+        "day02 simulation: {} score={}",
+        tournament.stats(),
+        tournament.total()
+    );
+    Ok(tournament.total())
+}
+
+/// Try every meaning for the guide's letters, reporting the highest and
+/// lowest scoring.
+pub fn brute_force(input: String, game: &str) -> Result<()> {
+    let game: Game = game.parse()?;
+    let rounds = parse_rounds(&input)?;
+    let mut scored = Decoder::all(&game, &rounds)?
+        .into_iter()
+        .map(|decoder| Ok((decoder.total(&game, &rounds)?, decoder)))
+        .collect::<Result<Vec<_>>>()?;
+    scored.sort_by_key(|(total, _)| *total);
+    if let (Some((low, worst)), Some((high, best))) = (scored.first(), scored.last()) {
+        println!(
+            "day02 highest: score={} guide={}",
+            high,
+            best.describe(&game)
+        );
+        println!(
+            "day02 lowest: score={} guide={}",
+            low,
+            worst.describe(&game)
+        );
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,6 +624,158 @@ mod tests {
     #[test]
     fn it_works() {
         let input = "A Y\nB X\nC Z";
-        assert_eq!(part1(input.to_string()).unwrap(), 15);
+        assert_eq!(part1(input.to_string(), GAME).unwrap(), 15);
+        assert_eq!(part2(input.to_string(), GAME).unwrap(), 12);
+    }
+
+    #[test]
+    fn rock_paper_scissors_rules() {
+        let game = Game::rock_paper_scissors();
+        let [rock, paper, scissors] = [Move(0), Move(1), Move(2)];
+        assert_eq!(game.outcome(scissors, rock), Outcome::Win);
+        assert_eq!(game.outcome(rock, paper), Outcome::Win);
+        assert_eq!(game.outcome(paper, scissors), Outcome::Win);
+        assert_eq!(game.outcome(paper, rock), Outcome::Lose);
+        assert_eq!(game.outcome(paper, paper), Outcome::Tie);
+        assert_eq!(game.should_play(rock, Outcome::Lose), Some(scissors));
+        assert_eq!(game.should_play(scissors, Outcome::Tie), Some(scissors));
+    }
+
+    #[test]
+    fn rock_paper_scissors_lizard_spock_rules() {
+        let game = Game::rock_paper_scissors_lizard_spock();
+        let by_name = |name: &str| game.find(name).unwrap();
+        let wins = [
+            ("Scissors", "Paper"),
+            ("Paper", "Rock"),
+            ("Rock", "Lizard"),
+            ("Lizard", "Spock"),
+            ("Spock", "Scissors"),
+            ("Scissors", "Lizard"),
+            ("Lizard", "Paper"),
+            ("Paper", "Spock"),
+            ("Spock", "Rock"),
+            ("Rock", "Scissors"),
+        ];
+        for (a, b) in wins {
+            assert_eq!(game.outcome(by_name(b), by_name(a)), Outcome::Win);
+            assert_eq!(game.outcome(by_name(a), by_name(b)), Outcome::Lose);
+        }
+        // Both Spock and Paper beat Rock, but Paper scores more.
+        assert_eq!(
+            game.should_play(by_name("Rock"), Outcome::Win),
+            Some(by_name("Paper"))
+        );
+        assert!(Game::cyclic(&["Rock", "Paper"]).is_err());
+    }
+
+    #[test]
+    fn games_from_config() {
+        let named: Game = "rock-paper-scissors-lizard-spock".parse().unwrap();
+        let listed: Game = "Rock, Spock, Paper, Lizard, Scissors".parse().unwrap();
+        assert_eq!(named.names, listed.names);
+        assert_eq!(named.beats, listed.beats);
+        assert!("Rock,Paper,rock".parse::<Game>().is_err());
+        assert!("Rock,,Paper".parse::<Game>().is_err());
+
+        let input = "A Y\nB X\nC Z";
+        let lizard_spock = "rock-paper-scissors-lizard-spock";
+        // Paper beats Rock, Rock loses to Paper, Scissors ties.
+        assert_eq!(part1(input.to_string(), lizard_spock).unwrap(), 9 + 1 + 8);
+        // Spock is the highest scoring way to lose to Paper and beat Scissors.
+        assert_eq!(part2(input.to_string(), lizard_spock).unwrap(), 4 + 2 + 8);
+    }
+
+    #[test]
+    fn decoders() {
+        let game = Game::rock_paper_scissors();
+        let rounds = parse_rounds("A Y\nB X\nC Z").unwrap();
+        let spec = "A=Rock,B=Paper,C=Scissors X=Lose,Y=Tie,Z=Win";
+        let decoder = Decoder::parse(spec, &game).unwrap();
+        assert_eq!(decoder.describe(&game), spec);
+        assert_eq!(decoder.total(&game, &rounds).unwrap(), 12);
+
+        let swapped = Decoder::parse("A=rock,B=scissors,C=paper X=Rock,Y=Paper,Z=Scissors", &game);
+        assert_eq!(
+            swapped.unwrap().total(&game, &rounds).unwrap(),
+            (2 + 6) + (1 + 6) + (3 + 6)
+        );
+
+        assert!(Decoder::parse("A=Rock X=Rock,Y=Win", &game).is_err());
+        assert!(Decoder::parse("A=Rock,A=Paper X=Rock", &game).is_err());
+        assert!(Decoder::parse("A=Rock X=Lose,X=Win", &game).is_err());
+        assert!(Decoder::parse("A=Rock,B=Paper X=Rock", &game)
+            .unwrap()
+            .total(&game, &rounds)
+            .is_err());
+    }
+
+    #[test]
+    fn brute_force_decoders() {
+        let game = Game::rock_paper_scissors();
+        let rounds = parse_rounds("A Y\nB X\nC Z").unwrap();
+        let decoders = Decoder::all(&game, &rounds).unwrap();
+        // 3! readings of the first column, and 3! + 3! of the second.
+        assert_eq!(decoders.len(), 6 * 12);
+        let best = decoders
+            .iter()
+            .map(|d| d.total(&game, &rounds).unwrap())
+            .max()
+            .unwrap();
+        // Winning every round, which takes each move once.
+        assert_eq!(best, 3 * 6 + 1 + 2 + 3);
+
+        let too_many = parse_rounds("A Y\nB X\nC Z\nD W").unwrap();
+        assert!(Decoder::all(&game, &too_many).is_err());
+        assert!(brute_force("A X\nB X\nC X\nD X".to_string(), GAME).is_err());
+        assert!(brute_force("A W\nA X\nA Y\nA Z".to_string(), GAME).is_err());
+        assert!(brute_force(
+            "A W\nA X\nA Y\nA Z".to_string(),
+            "rock-paper-scissors-lizard-spock"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn tournament_report() {
+        let game = Game::rock_paper_scissors();
+        let rounds = parse_rounds("A Y\nB X\nC Z").unwrap();
+        let decoder = Decoder::parse(GUIDE, &game).unwrap();
+        let tournament = Tournament::follow(&game, &decoder, &rounds).unwrap();
+        assert_eq!(tournament.total(), 12);
+        assert_eq!(
+            tournament.stats(),
+            Stats {
+                wins: 1,
+                losses: 1,
+                ties: 1
+            }
+        );
+        assert_eq!(
+            tournament.to_csv(&game),
+            "round,them,me,outcome,shape_score,outcome_score,total\n\
+             1,Rock,Rock,Tie,1,3,4\n\
+             2,Paper,Rock,Lose,1,0,5\n\
+             3,Scissors,Rock,Win,1,6,12\n"
+        );
+    }
+
+    #[test]
+    fn simulated_opponents() {
+        let game = Game::rock_paper_scissors();
+        let rounds = parse_rounds("A Y\nB X\nC Z").unwrap();
+        let decoder = Decoder::parse(GUIDE, &game).unwrap();
+        let mut rock = Opponent::parse("rock", &game).unwrap();
+        let tournament = Tournament::simulate(&game, &decoder, &rounds, &mut rock).unwrap();
+        // Tie with rock, lose to rock with scissors, beat rock with paper.
+        assert_eq!(tournament.total(), 4 + 3 + 8);
+
+        let run = |seed: &str| {
+            let mut opponent = Opponent::parse(seed, &game).unwrap();
+            Tournament::simulate(&game, &decoder, &rounds, &mut opponent).unwrap()
+        };
+        assert_eq!(run("random:7"), run("random:7"));
+        assert!(Opponent::parse("random:x", &game).is_err());
+        assert!(Opponent::parse("Spock", &game).is_err());
     }
 }
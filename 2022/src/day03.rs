@@ -1,71 +1,342 @@
 use anyhow::Result;
-use std::collections::HashSet;
 use std::str::FromStr;
 
-#[derive(Debug)]
-struct ParseSackError;
+/// A set of item types, one bit per type in order of priority.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    fn insert(&mut self, item: char) -> Result<(), InvalidItemError> {
+        self.0 |= 1 << (priority(&item)? - 1);
+        Ok(())
+    }
+
+    fn union(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 | other.0)
+    }
+
+    fn intersection(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// The priorities of the items in the set, lowest first.
+    fn priorities(&self) -> impl Iterator<Item = u32> {
+        let bits = self.0;
+        (0..52).filter(move |i| bits & (1 << i) != 0).map(|i| i + 1)
+    }
+
+    /// The items in the set, lowest priority first.
+    fn iter(&self) -> impl Iterator<Item = char> {
+        self.priorities().map(|p| match p {
+            1..=26 => (b'a' + p as u8 - 1) as char,
+            _ => (b'A' + p as u8 - 27) as char,
+        })
+    }
+
+    /// The items found in at least `n` of the sets.
+    fn common(sets: &[ItemSet], n: usize) -> ItemSet {
+        let mut common = ItemSet::default();
+        for bit in 0..52 {
+            let count = sets.iter().filter(|set| set.0 & (1 << bit) != 0).count();
+            if count >= n {
+                common.0 |= 1 << bit;
+            }
+        }
+        common
+    }
+}
+
+impl FromStr for ItemSet {
+    type Err = InvalidItemError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut set = ItemSet::default();
+        for item in s.chars() {
+            set.insert(item)?;
+        }
+        Ok(set)
+    }
+}
+
+/// A character which isn't one of the 52 item types.
+#[derive(Debug, PartialEq)]
+struct InvalidItemError(char);
+
+impl std::fmt::Display for InvalidItemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid item {:?}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidItemError {}
+
+#[derive(Debug, PartialEq)]
+enum ParseSackError {
+    /// The items can't be split evenly between the two compartments.
+    OddLength(usize),
+    NonAscii,
+    InvalidItem(InvalidItemError),
+}
+
+impl std::fmt::Display for ParseSackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseSackError::OddLength(len) => write!(f, "odd number of items ({})", len),
+            ParseSackError::NonAscii => write!(f, "non-ASCII items"),
+            ParseSackError::InvalidItem(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ParseSackError {}
+
+impl From<InvalidItemError> for ParseSackError {
+    fn from(e: InvalidItemError) -> Self {
+        ParseSackError::InvalidItem(e)
+    }
+}
+
+/// A bad sack in a list, with its line number counting from 1.
+#[derive(Debug, PartialEq)]
+struct ParseListError {
+    line: usize,
+    source: ParseSackError,
+}
+
+impl std::fmt::Display for ParseListError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid sack on line {}: {}", self.line, self.source)
+    }
+}
+
+impl std::error::Error for ParseListError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
 
 #[derive(Debug)]
-struct Sack(HashSet<char>, HashSet<char>);
+struct Sack(ItemSet, ItemSet);
+
+impl Sack {
+    fn items(&self) -> ItemSet {
+        self.0.union(self.1)
+    }
+}
 
 impl FromStr for Sack {
     type Err = ParseSackError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Splitting by bytes is only safe once every item is one byte.
+        if !s.is_ascii() {
+            return Err(ParseSackError::NonAscii);
+        }
+        if !s.len().is_multiple_of(2) {
+            return Err(ParseSackError::OddLength(s.len()));
+        }
         let (a, b) = s.split_at(s.len() / 2);
-        Ok(Sack(a.chars().collect(), b.chars().collect()))
+        Ok(Sack(a.parse()?, b.parse()?))
     }
 }
 
-fn priority(c: &char) -> u32 {
-    match *c as u32 {
-        x if (97..=127).contains(&x) => x - 97 + 1,
-        x if (65..=90).contains(&x) => x - 65 + 27,
-        _ => panic!(),
+fn parse_sacks(input: &str) -> Result<Vec<Sack>, ParseListError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse().map_err(|source| ParseListError {
+                line: i + 1,
+                source,
+            })
+        })
+        .collect()
+}
+
+fn priority(c: &char) -> Result<u32, InvalidItemError> {
+    match c {
+        'a'..='z' => Ok(*c as u32 - 'a' as u32 + 1),
+        'A'..='Z' => Ok(*c as u32 - 'A' as u32 + 27),
+        _ => Err(InvalidItemError(*c)),
     }
 }
 
-pub fn part1(input: String) -> Result<()> {
-    let total: u32 = input
-        .lines()
-        .map(Sack::from_str)
-        .map(Result::unwrap)
-        .map(|sack| {
-            let Sack(a, b) = sack;
-            match a.intersection(&b).next() {
-                Some(c) => priority(c),
-                None => 0,
-            }
-        })
+pub fn part1(input: String) -> Result<u32> {
+    let total: u32 = parse_sacks(&input)?
+        .into_iter()
+        .map(|Sack(a, b)| a.intersection(b).priorities().next().unwrap_or(0))
         .sum();
     println!("day03 part1: total={}", total);
-    Ok(())
+    Ok(total)
 }
 
-pub fn part2(input: String) -> Result<()> {
-    let sacks: Vec<HashSet<char>> = input
-        .lines()
-        .map(Sack::from_str)
-        .map(Result::unwrap)
-        .map(|sack| {
-            let Sack(a, b) = sack;
-            a.union(&b).cloned().collect()
-        })
-        .collect();
+/// What a group of elves have in common.
+#[derive(Debug, PartialEq)]
+enum Badge {
+    /// Exactly one item type is shared, so it must be the badge.
+    Found(ItemSet),
+    /// No item type is shared by the whole group.
+    Missing,
+    /// Several item types are shared, so the badge can't be told apart.
+    Ambiguous(ItemSet),
+    /// The list ran out before the group was full.
+    Incomplete(usize),
+}
 
-    let total: u32 = sacks
-        .chunks(3)
+impl std::fmt::Display for Badge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Badge::Found(item) => write!(f, "badge={}", item.iter().collect::<String>()),
+            Badge::Missing => write!(f, "anomaly: no shared items"),
+            Badge::Ambiguous(items) => write!(
+                f,
+                "anomaly: multiple shared items {}",
+                items.iter().collect::<String>()
+            ),
+            Badge::Incomplete(len) => write!(f, "anomaly: only {} sacks", len),
+        }
+    }
+}
+
+/// The badge of each group of `size` consecutive sacks.
+fn badges(sacks: &[ItemSet], size: usize) -> Result<Vec<Badge>> {
+    anyhow::ensure!(size > 0, "groups need at least one elf");
+    Ok(sacks
+        .chunks(size)
         .map(|grp| {
-            let shared = grp.iter().skip(1).fold(grp[0].clone(), |acc, sack| {
-                acc.intersection(sack).cloned().collect()
-            });
-            match shared.iter().next() {
-                Some(c) => priority(c),
-                None => 0,
+            let shared = ItemSet::common(grp, grp.len());
+            match shared.len() {
+                _ if grp.len() < size => Badge::Incomplete(grp.len()),
+                0 => Badge::Missing,
+                1 => Badge::Found(shared),
+                _ => Badge::Ambiguous(shared),
             }
         })
-        .sum();
+        .collect())
+}
+
+/// Sum the priorities of the badges of each group of `size` elves (3 for
+/// the puzzle), failing on the first group without a single badge.
+pub fn part2(input: String, size: usize) -> Result<u32> {
+    let sacks: Vec<ItemSet> = parse_sacks(&input)?.iter().map(Sack::items).collect();
+
+    let mut total = 0;
+    for (i, badge) in badges(&sacks, size)?.iter().enumerate() {
+        match badge {
+            Badge::Found(item) => total += item.priorities().sum::<u32>(),
+            anomaly => anyhow::bail!("group {}: {}", i + 1, anomaly),
+        }
+    }
 
     println!("day03 part2: total={}", total);
+    Ok(total)
+}
+
+/// Print each group's badge, flagging groups without exactly one.
+pub fn report(input: String, size: usize) -> Result<()> {
+    let sacks: Vec<ItemSet> = parse_sacks(&input)?.iter().map(Sack::items).collect();
+    let badges = badges(&sacks, size)?;
+    for (i, badge) in badges.iter().enumerate() {
+        println!("day03 group {}: {}", i + 1, badge);
+    }
+    let anomalies = badges
+        .iter()
+        .filter(|badge| !matches!(badge, Badge::Found(_)))
+        .count();
+    println!(
+        "day03 report: groups={} anomalies={}",
+        badges.len(),
+        anomalies
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw";
+
+    #[test]
+    fn it_works() {
+        assert_eq!(part1(EXAMPLE.to_string()).unwrap(), 157);
+        assert_eq!(part2(EXAMPLE.to_string(), 3).unwrap(), 70);
+    }
+
+    #[test]
+    fn item_sets() {
+        let a: ItemSet = "abcZ".parse().unwrap();
+        let b: ItemSet = "cdZ".parse().unwrap();
+        assert_eq!(a.intersection(b).iter().collect::<String>(), "cZ");
+        assert_eq!(a.union(b).len(), 5);
+        assert_eq!(a.priorities().collect::<Vec<_>>(), vec![1, 2, 3, 52]);
+
+        let c: ItemSet = "axy".parse().unwrap();
+        assert_eq!(
+            ItemSet::common(&[a, b, c], 2).iter().collect::<String>(),
+            "acZ"
+        );
+        assert_eq!(ItemSet::common(&[a, b, c], 3), ItemSet::default());
+    }
+
+    #[test]
+    fn priorities() {
+        assert_eq!(priority(&'a'), Ok(1));
+        assert_eq!(priority(&'z'), Ok(26));
+        assert_eq!(priority(&'A'), Ok(27));
+        assert_eq!(priority(&'Z'), Ok(52));
+        assert_eq!(priority(&'{'), Err(InvalidItemError('{')));
+        assert_eq!(priority(&'1'), Err(InvalidItemError('1')));
+    }
+
+    #[test]
+    fn rejects_bad_sacks() {
+        let err = |s: &str| s.parse::<Sack>().err().unwrap();
+        assert_eq!(err("abc"), ParseSackError::OddLength(3));
+        assert_eq!(err("aébc"), ParseSackError::NonAscii);
+        assert_eq!(
+            err("ab{c"),
+            ParseSackError::InvalidItem(InvalidItemError('{'))
+        );
+
+        let err = parse_sacks("abcd\nab~c\n").err().unwrap();
+        assert_eq!(err.line, 2);
+        assert_eq!(err.to_string(), "invalid sack on line 2: invalid item '~'");
+        assert!(part1("ab\nabc".to_string()).is_err());
+    }
+
+    #[test]
+    fn group_badges() {
+        let sacks: Vec<ItemSet> = parse_sacks(EXAMPLE)
+            .unwrap()
+            .iter()
+            .map(Sack::items)
+            .collect();
+        let found = |items: &str| Badge::Found(items.parse().unwrap());
+        assert_eq!(badges(&sacks, 3).unwrap(), vec![found("r"), found("Z")]);
+        assert_eq!(
+            badges(&sacks, 4).unwrap(),
+            vec![Badge::Missing, Badge::Incomplete(2)]
+        );
+        assert_eq!(
+            badges(&sacks[..2], 2).unwrap(),
+            vec![Badge::Ambiguous("frsFM".parse().unwrap())]
+        );
+        assert!(badges(&sacks, 0).is_err());
+
+        let err = part2(EXAMPLE.to_string(), 2).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "group 1: anomaly: multiple shared items frsFM"
+        );
+    }
+}
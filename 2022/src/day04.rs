@@ -1,68 +1,564 @@
 use std::num::ParseIntError;
+use std::ops::Range;
 use std::str::FromStr;
 
 use anyhow::Result;
 
-#[derive(Debug)]
+/// Why a line of assignments couldn't be parsed. Columns count characters
+/// from 0.
+#[derive(Debug, PartialEq)]
 enum ParseError {
-    InvalidFormat,
-    Parse(ParseIntError),
+    MissingSeparator {
+        separator: char,
+        column: usize,
+    },
+    /// A range whose first section comes after its last.
+    Inverted {
+        start: usize,
+        end: usize,
+        column: usize,
+    },
+    /// A section too large to mark the end of a range after.
+    TooLarge {
+        section: usize,
+        column: usize,
+    },
+    Parse {
+        column: usize,
+        source: ParseIntError,
+    },
 }
 
-impl From<ParseIntError> for ParseError {
-    fn from(err: ParseIntError) -> Self {
-        ParseError::Parse(err)
+impl ParseError {
+    /// The same error, for text found `offset` characters further along.
+    fn offset(self, offset: usize) -> Self {
+        match self {
+            ParseError::MissingSeparator { separator, column } => ParseError::MissingSeparator {
+                separator,
+                column: column + offset,
+            },
+            ParseError::Inverted { start, end, column } => ParseError::Inverted {
+                start,
+                end,
+                column: column + offset,
+            },
+            ParseError::TooLarge { section, column } => ParseError::TooLarge {
+                section,
+                column: column + offset,
+            },
+            ParseError::Parse { column, source } => ParseError::Parse {
+                column: column + offset,
+                source,
+            },
+        }
     }
 }
-struct Section(std::ops::RangeInclusive<usize>);
 
-impl FromStr for Section {
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MissingSeparator { separator, column } => {
+                write!(f, "missing '{}' after column {}", separator, column)
+            }
+            ParseError::Inverted { start, end, column } => {
+                write!(f, "inverted range {}-{} at column {}", start, end, column)
+            }
+            ParseError::TooLarge { section, column } => {
+                write!(f, "section {} at column {} is too large", section, column)
+            }
+            ParseError::Parse { column, source } => {
+                write!(f, "invalid section at column {}: {}", column, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// A bad line of assignments, with its line number counting from 1.
+#[derive(Debug, PartialEq)]
+struct ParseListError {
+    line: usize,
+    source: ParseError,
+}
+
+impl std::fmt::Display for ParseListError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid pair on line {}: {}", self.line, self.source)
+    }
+}
+
+impl std::error::Error for ParseListError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// A set of sections, kept as sorted, disjoint and non-adjacent ranges.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct IntervalSet {
+    // Half-open, so adjacent ranges and differences need no +1/-1 fiddling.
+    ranges: Vec<Range<usize>>,
+}
+
+impl IntervalSet {
+    fn from_ranges(ranges: impl IntoIterator<Item = Range<usize>>) -> Self {
+        let mut ranges: Vec<_> = ranges.into_iter().filter(|r| !r.is_empty()).collect();
+        ranges.sort_by_key(|r| r.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        IntervalSet { ranges: merged }
+    }
+
+    /// The sections from `start` to `end`, inclusive. `end` must be below
+    /// `usize::MAX`, which parsing checks.
+    fn span(start: usize, end: usize) -> Self {
+        IntervalSet::from_ranges(std::iter::once(start..end + 1))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The number of sections in the set.
+    fn len(&self) -> usize {
+        self.ranges.iter().map(|r| r.len()).sum()
+    }
+
+    /// The first and last sections, if any.
+    fn bounds(&self) -> Option<(usize, usize)> {
+        Some((self.ranges.first()?.start, self.ranges.last()?.end - 1))
+    }
+
+    fn union(&self, other: &IntervalSet) -> IntervalSet {
+        IntervalSet::from_ranges(self.ranges.iter().chain(&other.ranges).cloned())
+    }
+
+    fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while let (Some(a), Some(b)) = (self.ranges.get(i), other.ranges.get(j)) {
+            let (start, end) = (a.start.max(b.start), a.end.min(b.end));
+            if start < end {
+                ranges.push(start..end);
+            }
+            // Whichever range ends first can't overlap anything later.
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges = Vec::new();
+        for range in &self.ranges {
+            let mut start = range.start;
+            for cut in other.ranges.iter().filter(|cut| cut.start < range.end) {
+                if cut.end <= start {
+                    continue;
+                }
+                if cut.start > start {
+                    ranges.push(start..cut.start);
+                }
+                start = start.max(cut.end);
+            }
+            if start < range.end {
+                ranges.push(start..range.end);
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    /// Whether every section of `other` is also in this set.
+    fn contains(&self, other: &IntervalSet) -> bool {
+        other.difference(self).is_empty()
+    }
+
+    fn overlaps(&self, other: &IntervalSet) -> bool {
+        !self.intersection(other).is_empty()
+    }
+}
+
+impl std::fmt::Display for IntervalSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ranges: Vec<_> = self
+            .ranges
+            .iter()
+            .map(|r| format!("{}-{}", r.start, r.end - 1))
+            .collect();
+        write!(f, "{}", ranges.join(","))
+    }
+}
+
+impl FromStr for IntervalSet {
     type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (a, b) = s.split_once('-').ok_or(ParseError::InvalidFormat)?;
-        Ok(Section(a.parse()?..=b.parse()?))
+        let (a, b) = s.split_once('-').ok_or(ParseError::MissingSeparator {
+            separator: '-',
+            column: s.chars().count(),
+        })?;
+        let section = |s: &str, column| {
+            s.parse()
+                .map_err(|source| ParseError::Parse { column, source })
+        };
+        let (start, end) = (section(a, 0)?, section(b, a.chars().count() + 1)?);
+        // Ranges are stored half-open, so the last section needs a successor.
+        if end == usize::MAX {
+            return Err(ParseError::TooLarge {
+                section: end,
+                column: a.chars().count() + 1,
+            });
+        }
+        if start > end {
+            return Err(ParseError::Inverted {
+                start,
+                end,
+                column: 0,
+            });
+        }
+        Ok(IntervalSet::span(start, end))
     }
 }
 
-struct Pair(Section, Section);
+struct Pair(IntervalSet, IntervalSet);
 
 impl FromStr for Pair {
     type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (left, right) = s.split_once(',').ok_or(ParseError::InvalidFormat)?;
-        Ok(Pair(left.parse()?, right.parse()?))
+        let (left, right) = s.split_once(',').ok_or(ParseError::MissingSeparator {
+            separator: ',',
+            column: s.chars().count(),
+        })?;
+        let offset = left.chars().count() + 1;
+        let left = left.parse()?;
+        let right = right.parse().map_err(|e: ParseError| e.offset(offset))?;
+        Ok(Pair(left, right))
     }
 }
 
-pub fn part1(input: String) -> Result<()> {
-    let total: usize = input
+fn parse_pairs(input: &str) -> Result<Vec<Pair>, ParseListError> {
+    input
         .lines()
-        .map(Pair::from_str)
-        .map(Result::unwrap)
-        .filter(|pair| {
-            let Pair(Section(a), Section(b)) = pair;
-            let (larger, smaller) = if a.end() - a.start() > b.end() - b.start() {
-                (a, b)
-            } else {
-                (b, a)
-            };
-            larger.contains(smaller.start()) && larger.contains(smaller.end())
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse().map_err(|source| ParseListError {
+                line: i + 1,
+                source,
+            })
         })
+        .collect()
+}
+
+pub fn part1(input: String) -> Result<usize> {
+    let total = parse_pairs(&input)?
+        .iter()
+        .filter(|Pair(a, b)| a.contains(b) || b.contains(a))
         .count();
     println!("day04 part1: total={}", total);
-    Ok(())
+    Ok(total)
 }
 
-pub fn part2(input: String) -> Result<()> {
-    let total: usize = input
-        .lines()
-        .map(Pair::from_str)
-        .map(Result::unwrap)
-        .filter(|pair| {
-            let Pair(Section(a), Section(b)) = pair;
-            a.start() <= b.end() && b.start() <= a.end()
-        })
+pub fn part2(input: String) -> Result<usize> {
+    let total = parse_pairs(&input)?
+        .iter()
+        .filter(|Pair(a, b)| a.overlaps(b))
         .count();
     println!("day04 part2: total={}", total);
-    Ok(())
+    Ok(total)
+}
+
+/// The sections assigned to at least one elf, across every pair.
+fn covered(pairs: &[Pair]) -> IntervalSet {
+    // Merge everything at once, rather than growing one set pair by pair.
+    IntervalSet::from_ranges(pairs.iter().flat_map(|Pair(a, b)| a.union(b).ranges))
+}
+
+/// Report how many sections are covered by some elf, and which sections
+/// between the first and last covered are left uncovered.
+pub fn coverage(input: String) -> Result<usize> {
+    let covered = covered(&parse_pairs(&input)?);
+    let uncovered = covered
+        .bounds()
+        .map_or_else(IntervalSet::default, |(start, end)| {
+            IntervalSet::span(start, end).difference(&covered)
+        });
+    println!(
+        "day04 coverage: covered={} uncovered={} [{}]",
+        covered.len(),
+        uncovered.len(),
+        uncovered
+    );
+    Ok(covered.len())
+}
+
+/// The first and last section of every elf, two per pair.
+fn elves(pairs: &[Pair]) -> Vec<(usize, usize)> {
+    pairs
+        .iter()
+        .flat_map(|Pair(a, b)| [a.bounds(), b.bounds()])
+        .flatten()
+        .collect()
+}
+
+/// How many elves are assigned to each stretch of sections.
+struct Depths {
+    /// Consecutive stretches with the same (non-zero) number of elves.
+    segments: Vec<(Range<usize>, usize)>,
+}
+
+impl Depths {
+    /// Sweep across the sections, counting elves in as their assignments
+    /// start and out as they end. Each `end` must be below `usize::MAX`,
+    /// as it is for any parsed assignment.
+    fn sweep(elves: &[(usize, usize)]) -> Self {
+        let mut events: Vec<(usize, isize)> = elves
+            .iter()
+            .flat_map(|&(start, end)| [(start, 1), (end + 1, -1)])
+            .collect();
+        events.sort_unstable();
+        let mut segments = Vec::new();
+        let mut depth = 0;
+        for (i, &(section, change)) in events.iter().enumerate() {
+            depth += change;
+            match events.get(i + 1) {
+                Some(&(next, _)) if next > section && depth > 0 => {
+                    segments.push((section..next, depth as usize))
+                }
+                _ => {}
+            }
+        }
+        Depths { segments }
+    }
+
+    /// The most elves assigned to any one section.
+    fn max(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|(_, depth)| *depth)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The sections assigned to more than `k` elves.
+    fn more_than(&self, k: usize) -> IntervalSet {
+        IntervalSet::from_ranges(
+            self.segments
+                .iter()
+                .filter(|(_, depth)| *depth > k)
+                .map(|(range, _)| range.clone()),
+        )
+    }
+}
+
+/// The elves whose sections are all assigned to some other single elf, by
+/// index into `elves`.
+fn redundant(elves: &[(usize, usize)]) -> Vec<usize> {
+    // Widest first among equal starts, so anything containing an elf comes
+    // before it.
+    let mut order: Vec<usize> = (0..elves.len()).collect();
+    order.sort_by_key(|&i| (elves[i].0, std::cmp::Reverse(elves[i].1)));
+    let mut redundant = Vec::new();
+    let mut furthest = None;
+    for group in order.chunk_by(|&i, &j| elves[i] == elves[j]) {
+        let (_, end) = elves[group[0]];
+        // Identical assignments contain each other.
+        if group.len() > 1 || furthest.is_some_and(|f| f >= end) {
+            redundant.extend(group);
+        }
+        furthest = furthest.max(Some(end));
+    }
+    redundant.sort_unstable();
+    redundant
+}
+
+/// Analyse every elf at once: the most elves on any section, the sections
+/// with more than `k` elves, and the elves made redundant by another.
+pub fn analyse(input: String, k: usize) -> Result<usize> {
+    let elves = elves(&parse_pairs(&input)?);
+    let depths = Depths::sweep(&elves);
+    let crowded = depths.more_than(k);
+    println!(
+        "day04 analysis: max_depth={} more_than_{}={} [{}]",
+        depths.max(),
+        k,
+        crowded.len(),
+        crowded
+    );
+    let redundant = redundant(&elves);
+    let names: Vec<_> = redundant
+        .iter()
+        .map(|i| format!("{}.{}", i / 2 + 1, i % 2 + 1))
+        .collect();
+    println!(
+        "day04 redundant elves: count={} [{}]",
+        redundant.len(),
+        names.join(",")
+    );
+    Ok(depths.max())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8";
+
+    fn set(s: &str) -> IntervalSet {
+        s.split(',')
+            .map(|r| r.parse::<IntervalSet>().unwrap())
+            .fold(IntervalSet::default(), |acc, r| acc.union(&r))
+    }
+
+    #[test]
+    fn it_works() {
+        assert_eq!(part1(EXAMPLE.to_string()).unwrap(), 2);
+        assert_eq!(part2(EXAMPLE.to_string()).unwrap(), 4);
+        assert_eq!(coverage(EXAMPLE.to_string()).unwrap(), 8);
+    }
+
+    #[test]
+    fn interval_set_algebra() {
+        let a = set("2-4,8-10");
+        let b = set("4-8");
+        assert_eq!(set("2-3,4-5"), set("2-5"));
+        assert_eq!(a.union(&b), set("2-10"));
+        assert_eq!(a.intersection(&b), set("4-4,8-8"));
+        assert_eq!(a.difference(&b), set("2-3,9-10"));
+        assert_eq!(b.difference(&a), set("5-7"));
+        assert_eq!(a.len(), 6);
+        assert_eq!(a.bounds(), Some((2, 10)));
+        assert_eq!(a.to_string(), "2-4,8-10");
+
+        assert!(set("1-10").contains(&a));
+        assert!(!a.contains(&b));
+        assert!(a.overlaps(&b));
+        assert!(!set("1-3").overlaps(&set("4-6")));
+        assert!(a.contains(&IntervalSet::default()));
+    }
+
+    #[test]
+    fn uncovered_sections() {
+        let pairs = parse_pairs("2-3,8-9\n5-5,3-4").unwrap();
+        let covered = covered(&pairs);
+        assert_eq!(covered, set("2-5,8-9"));
+        assert_eq!(set("2-9").difference(&covered), set("6-7"));
+    }
+
+    #[test]
+    fn sweep_depths() {
+        let elves = elves(&parse_pairs(EXAMPLE).unwrap());
+        let depths = Depths::sweep(&elves);
+        // Eight of the twelve elves are assigned section 6.
+        assert_eq!(depths.max(), 8);
+        assert_eq!(depths.more_than(5), set("4-7"));
+        assert_eq!(depths.more_than(0), set("2-9"));
+        assert_eq!(Depths::sweep(&[]).max(), 0);
+        assert_eq!(
+            Depths::sweep(&[(1, 2), (5, 5)]).more_than(0),
+            set("1-2,5-5")
+        );
+    }
+
+    #[test]
+    fn redundant_elves() {
+        assert_eq!(redundant(&[(2, 4), (3, 4), (1, 2), (6, 6)]), vec![1]);
+        assert_eq!(redundant(&[(2, 4), (2, 4), (1, 5)]), vec![0, 1]);
+        assert_eq!(redundant(&[(3, 5), (2, 4)]), Vec::<usize>::new());
+        assert_eq!(analyse(EXAMPLE.to_string(), 2).unwrap(), 8);
+    }
+
+    #[test]
+    fn rejects_bad_ranges() {
+        let err = |s: &str| s.parse::<Pair>().err().unwrap();
+        assert_eq!(
+            err("2-4,8-6"),
+            ParseError::Inverted {
+                start: 8,
+                end: 6,
+                column: 4
+            }
+        );
+        assert_eq!(
+            err("2-4;6-8"),
+            ParseError::MissingSeparator {
+                separator: ',',
+                column: 7
+            }
+        );
+        assert_eq!(
+            err("2-4,68"),
+            ParseError::MissingSeparator {
+                separator: '-',
+                column: 6
+            }
+        );
+        assert_eq!(
+            err("0-18446744073709551615,1-2"),
+            ParseError::TooLarge {
+                section: usize::MAX,
+                column: 2
+            }
+        );
+        assert_eq!(
+            err("1-2,3-18446744073709551615"),
+            ParseError::TooLarge {
+                section: usize::MAX,
+                column: 6
+            }
+        );
+        assert!(matches!(
+            err("x-1,y-2"),
+            ParseError::Parse { column: 0, .. }
+        ));
+        // Columns count characters, not bytes.
+        assert_eq!(
+            err("1-2,3é"),
+            ParseError::MissingSeparator {
+                separator: '-',
+                column: 6
+            }
+        );
+        assert!(matches!(
+            err("1-é,3-4"),
+            ParseError::Parse { column: 2, .. }
+        ));
+        assert!(matches!(
+            err("é-1,3-4"),
+            ParseError::Parse { column: 0, .. }
+        ));
+        assert!(matches!(
+            err("1-2é,3-x"),
+            ParseError::Parse { column: 2, .. }
+        ));
+        assert!(matches!(
+            err("1-2,3-é"),
+            ParseError::Parse { column: 6, .. }
+        ));
+        assert_eq!(
+            err("2-x,6-8").to_string(),
+            "invalid section at column 2: invalid digit found in string"
+        );
+
+        let err = parse_pairs("2-4,6-8\n\n").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "invalid pair on line 2: missing ',' after column 0"
+        );
+        assert!(part1("2-4,6-8\n9-1,1-9".to_string()).is_err());
+    }
 }
@@ -60,28 +60,156 @@ fn parse_input(input: String) -> (Vec<Stack>, Vec<Move>) {
     (parse_stacks(header), moves)
 }
 
-pub fn part1(input: String) -> Result<()> {
-    let (mut stacks, moves) = parse_input(input);
+/// How a crane carries crates between stacks.
+trait Crane {
+    /// The order, bottom to top, that crates land in when lifted from the
+    /// top of a stack, given in their order on that stack.
+    fn arrange(&self, crates: Vec<char>) -> Vec<char>;
+}
+
+/// Moves one crate at a time, reversing their order.
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn arrange(&self, mut crates: Vec<char>) -> Vec<char> {
+        crates.reverse();
+        crates
+    }
+}
+
+/// Moves every crate at once, keeping their order.
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn arrange(&self, crates: Vec<char>) -> Vec<char> {
+        crates
+    }
+}
+
+/// Moves at most this many crates at once, taking from the top each time.
+struct Limited(usize);
+
+impl Limited {
+    fn new(limit: usize) -> Result<Self> {
+        anyhow::ensure!(limit > 0, "A crane must move at least one crate at once");
+        Ok(Limited(limit))
+    }
+}
+
+impl Crane for Limited {
+    fn arrange(&self, crates: Vec<char>) -> Vec<char> {
+        crates
+            .rchunks(self.0)
+            .flat_map(|chunk| chunk.iter().copied())
+            .collect()
+    }
+}
+
+/// Apply each move in turn with the given crane.
+fn simulate(stacks: &mut [Stack], moves: &[Move], crane: &dyn Crane) -> Result<()> {
     for m in moves {
-        for _ in 0..m.count {
-            if let Some(c) = stacks[m.from - 1].pop() {
-                stacks[m.to - 1].push(c);
-            }
-        }
+        // Check the whole move before touching either stack.
+        let (from, to) = (m.from.wrapping_sub(1), m.to.wrapping_sub(1));
+        anyhow::ensure!(from < stacks.len(), "No stack {} to move from", m.from);
+        anyhow::ensure!(to < stacks.len(), "No stack {} to move to", m.to);
+        let start = stacks[from].len().checked_sub(m.count).ok_or_else(|| {
+            anyhow::anyhow!("Can't move {} crates from stack {}", m.count, m.from)
+        })?;
+        let crates = crane.arrange(stacks[from].drain(start..).collect());
+        stacks[to].extend(crates);
     }
-    let results: Option<String> = stacks.iter().map(|s| s.last()).collect();
-    println!("day05 part1: {}", results.unwrap());
     Ok(())
 }
 
-pub fn part2(input: String) -> Result<()> {
+/// The crate on top of each stack after rearranging them with `crane`.
+fn rearrange(input: String, crane: &dyn Crane) -> Result<String> {
     let (mut stacks, moves) = parse_input(input);
-    for m in moves {
-        let start = stacks[m.from - 1].len() - m.count;
-        let mut crates: Vec<_> = stacks[m.from - 1].drain(start..).collect();
-        stacks[m.to - 1].append(&mut crates);
+    simulate(&mut stacks, &moves, crane)?;
+    stacks
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            s.last()
+                .ok_or_else(|| anyhow::anyhow!("Stack {} is empty", i + 1))
+        })
+        .collect()
+}
+
+pub fn part1(input: String) -> Result<String> {
+    let results = rearrange(input, &CrateMover9000)?;
+    println!("day05 part1: {}", results);
+    Ok(results)
+}
+
+pub fn part2(input: String) -> Result<String> {
+    let results = rearrange(input, &CrateMover9001)?;
+    println!("day05 part2: {}", results);
+    Ok(results)
+}
+
+/// Rearrange with a crane that moves at most `limit` crates at once.
+pub fn limited(input: String, limit: usize) -> Result<String> {
+    let results = rearrange(input, &Limited::new(limit)?)?;
+    println!("day05 limited to {}: {}", limit, results);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+
+    #[test]
+    fn it_works() {
+        assert_eq!(part1(EXAMPLE.to_string()).unwrap(), "CMZ");
+        assert_eq!(part2(EXAMPLE.to_string()).unwrap(), "MCD");
+    }
+
+    #[test]
+    fn crane_models() {
+        let crates = vec!['a', 'b', 'c', 'd', 'e'];
+        assert_eq!(
+            CrateMover9000.arrange(crates.clone()),
+            vec!['e', 'd', 'c', 'b', 'a']
+        );
+        assert_eq!(CrateMover9001.arrange(crates.clone()), crates);
+        assert_eq!(
+            Limited::new(2).unwrap().arrange(crates.clone()),
+            vec!['d', 'e', 'b', 'c', 'a']
+        );
+        assert_eq!(
+            Limited::new(1).unwrap().arrange(crates.clone()),
+            CrateMover9000.arrange(crates.clone())
+        );
+        assert_eq!(Limited::new(5).unwrap().arrange(crates.clone()), crates);
+        assert_eq!(limited(EXAMPLE.to_string(), 1).unwrap(), "CMZ");
+        assert_eq!(limited(EXAMPLE.to_string(), 3).unwrap(), "MCD");
+        assert!(Limited::new(0).is_err());
+        assert!(limited(EXAMPLE.to_string(), 0).is_err());
+    }
+
+    #[test]
+    fn rejects_impossible_moves() {
+        let mut stacks = vec![vec!['a'], vec![]];
+        for bad in [
+            "move 2 from 1 to 2",
+            "move 1 from 3 to 2",
+            "move 1 from 0 to 2",
+            "move 1 from 1 to 0",
+            "move 1 from 1 to 3",
+        ] {
+            let moves = vec![bad.parse::<Move>().unwrap()];
+            assert!(simulate(&mut stacks, &moves, &CrateMover9000).is_err());
+            assert_eq!(stacks, vec![vec!['a'], vec![]], "{}", bad);
+        }
     }
-    let results: Option<String> = stacks.iter().map(|s| s.last()).collect();
-    println!("day05 part2: {}", results.unwrap());
-    Ok(())
 }
@@ -11,24 +11,53 @@ fn main() -> Result<()> {
     if let Some(arg) = std::env::args().nth(1) {
         match arg.as_str() {
             "day01" => {
-                day01::part1(read_input(1));
-                day01::part2(read_input(1));
+                let top = flag("--top").map_or(Ok(3), |k| k.parse())?;
+                day01::part1(open_input(1)?)?;
+                day01::part2(open_input(1)?, top)?;
             }
             "day02" => {
-                day02::part1(read_input(2))?;
-                day02::part2(read_input(2))?;
+                let game = flag("--game").unwrap_or_else(|| day02::GAME.to_string());
+                day02::part1(read_input(2), &game)?;
+                day02::part2(read_input(2), &game)?;
+                if let Some(guide) = flag("--guide") {
+                    day02::decode(read_input(2), &game, &guide)?;
+                }
+                if std::env::args().any(|arg| arg == "--brute-force") {
+                    day02::brute_force(read_input(2), &game)?;
+                }
+                let guide = flag("--guide").unwrap_or_else(|| day02::GUIDE.to_string());
+                let csv = flag("--csv");
+                if csv.is_some() || std::env::args().any(|arg| arg == "--report") {
+                    day02::report(read_input(2), &game, &guide, csv)?;
+                }
+                if let Some(opponent) = flag("--opponent") {
+                    day02::simulate(read_input(2), &game, &guide, &opponent)?;
+                }
             }
             "day03" => {
+                let group = flag("--group").map_or(Ok(3), |n| n.parse())?;
                 day03::part1(read_input(3))?;
-                day03::part2(read_input(3))?;
+                day03::part2(read_input(3), group)?;
+                if std::env::args().any(|arg| arg == "--badges") {
+                    day03::report(read_input(3), group)?;
+                }
             }
             "day04" => {
                 day04::part1(read_input(4))?;
                 day04::part2(read_input(4))?;
+                if std::env::args().any(|arg| arg == "--coverage") {
+                    day04::coverage(read_input(4))?;
+                }
+                if let Some(k) = flag("--more-than") {
+                    day04::analyse(read_input(4), k.parse()?)?;
+                }
             }
             "day05" => {
                 day05::part1(read_input(5))?;
                 day05::part2(read_input(5))?;
+                if let Some(limit) = flag("--crane-limit") {
+                    day05::limited(read_input(5), limit.parse()?)?;
+                }
             }
             "day06" => {
                 day06::part1(read_input(6))?;
@@ -42,6 +71,17 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// The value following a flag on the command line, e.g `--top 5`.
+fn flag(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next().and(args.next())
+}
+
+/// The input for a day, to be read incrementally.
+fn open_input(day: usize) -> std::io::Result<std::io::BufReader<std::fs::File>> {
+    std::fs::File::open(format!("./data/day{:0>2}.txt", day)).map(std::io::BufReader::new)
+}
+
 fn read_input(day: usize) -> String {
     std::fs::read_to_string(format!("./data/day{:0>2}.txt", day)).unwrap()
 }
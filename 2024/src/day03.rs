@@ -1,13 +1,17 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::ops::RangeInclusive;
+
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::{anychar, u32},
-    combinator::{iterator, map},
-    sequence::{delimited, separated_pair},
-    IResult, Parser,
+    bytes::streaming::{tag, tag_no_case},
+    character::streaming::{anychar, digit1, multispace0},
+    combinator::{iterator, map, map_res, recognize, verify},
+    sequence::{pair, preceded, separated_pair, terminated},
+    IResult,
 };
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum State {
     Enabled,
     Disabled,
@@ -15,6 +19,7 @@ enum State {
 
 struct InstructionSet(Vec<Instruction>);
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Instruction {
     Multiply((u32, u32)),
     StateChange(State),
@@ -23,68 +28,323 @@ enum Instruction {
 impl std::str::FromStr for InstructionSet {
     type Err = ();
     fn from_str(seq: &str) -> Result<Self, Self::Err> {
-        Ok(Self(
-            iterator(seq, corrupted_instruction).flatten().collect(),
-        ))
+        Ok(Self::parse_with(seq, &Grammar::default()))
     }
 }
 
-/// Try to parse a "mul(a, b)" token from the input sequence.
-///
-/// e.g, "mul(2, 3)" -> (2, 3)
-fn multiply(seq: &str) -> IResult<&str, (u32, u32)> {
-    delimited(tag("mul("), separated_pair(u32, tag(","), u32), tag(")")).parse(seq)
+impl InstructionSet {
+    /// Parse every instruction the grammar accepts from the input sequence.
+    fn parse_with(seq: &str, grammar: &Grammar) -> Self {
+        Self(
+            iterator(seq, |s| grammar.corrupted_instruction(s))
+                .flatten()
+                .collect(),
+        )
+    }
 }
 
-/// Try to parse a "do()" or "don't()" token from the input sequence.
+/// Which tokens count as instructions.
 ///
-/// e.g, "do()" -> State::Enabled
-/// e.g, "don't()" -> State::Disabled
-fn state_change(seq: &str) -> IResult<&str, State> {
-    alt((
-        map(tag("do()"), |_| State::Enabled),
-        map(tag("don't()"), |_| State::Disabled),
-    ))
-    .parse(seq)
+/// The default accepts any `u32` operand, while [`Grammar::strict`] matches
+/// the puzzle's description exactly.
+#[derive(Debug, Clone)]
+struct Grammar {
+    /// Allowed number of digits in each "mul" operand.
+    digits: RangeInclusive<usize>,
+    /// Whether whitespace may appear between the parts of a token.
+    whitespace: bool,
+    /// Whether instruction names must be lowercase.
+    case_sensitive: bool,
+}
+
+impl Default for Grammar {
+    fn default() -> Self {
+        Grammar {
+            digits: 1..=usize::MAX,
+            whitespace: false,
+            case_sensitive: true,
+        }
+    }
+}
+
+impl Grammar {
+    /// Operands of 1-3 digits, no whitespace and lowercase names.
+    fn strict() -> Self {
+        Grammar {
+            digits: 1..=3,
+            ..Grammar::default()
+        }
+    }
+
+    /// Try to parse an instruction name from the input sequence.
+    fn name<'a>(&self, name: &'static str, seq: &'a str) -> IResult<&'a str, &'a str> {
+        if self.case_sensitive {
+            tag(name)(seq)
+        } else {
+            tag_no_case(name)(seq)
+        }
+    }
+
+    /// Skip any whitespace before the next part of a token, if allowed.
+    fn space<'a>(&self, seq: &'a str) -> IResult<&'a str, &'a str> {
+        if self.whitespace {
+            multispace0(seq)
+        } else {
+            Ok((seq, ""))
+        }
+    }
+
+    /// Try to parse a piece of punctuation from the input sequence.
+    fn punct<'a>(&self, punct: &'static str, seq: &'a str) -> IResult<&'a str, &'a str> {
+        preceded(|s| self.space(s), tag(punct))(seq)
+    }
+
+    /// Try to parse a "mul" operand within the allowed number of digits.
+    fn operand<'a>(&self, seq: &'a str) -> IResult<&'a str, u32> {
+        preceded(
+            |s| self.space(s),
+            map_res(
+                verify(digit1, |d: &str| self.digits.contains(&d.len())),
+                str::parse,
+            ),
+        )(seq)
+    }
+
+    /// Try to parse a "mul(a,b)" token from the input sequence.
+    ///
+    /// e.g, "mul(2,3)" -> (2, 3)
+    fn multiply<'a>(&self, seq: &'a str) -> IResult<&'a str, (u32, u32)> {
+        preceded(
+            pair(|s| self.name("mul", s), |s| self.punct("(", s)),
+            terminated(
+                separated_pair(
+                    |s| self.operand(s),
+                    |s| self.punct(",", s),
+                    |s| self.operand(s),
+                ),
+                |s| self.punct(")", s),
+            ),
+        )(seq)
+    }
+
+    /// Try to parse a "do()" or "don't()" token from the input sequence.
+    ///
+    /// e.g, "do()" -> State::Enabled
+    /// e.g, "don't()" -> State::Disabled
+    fn state_change<'a>(&self, seq: &'a str) -> IResult<&'a str, State> {
+        let call = |name| {
+            move |s| {
+                let (s, _) = self.name(name, s)?;
+                let (s, _) = self.punct("(", s)?;
+                self.punct(")", s)
+            }
+        };
+        alt((
+            map(call("do"), |_| State::Enabled),
+            map(call("don't"), |_| State::Disabled),
+        ))(seq)
+    }
+
+    /// Try to parse an instruction from the input sequence.
+    fn instruction<'a>(&self, seq: &'a str) -> IResult<&'a str, Instruction> {
+        alt((
+            map(|s| self.multiply(s), Instruction::Multiply),
+            map(|s| self.state_change(s), Instruction::StateChange),
+        ))(seq)
+    }
+
+    /// Try to parse a corrupted instruction from the input sequence.
+    fn corrupted_instruction<'a>(&self, seq: &'a str) -> IResult<&'a str, Option<Instruction>> {
+        alt((map(|s| self.instruction(s), Some), map(anychar, |_| None)))(seq)
+    }
+
+    /// The text of every token accepted as an instruction, in order.
+    fn tokens<'a>(&self, seq: &'a str) -> Vec<&'a str> {
+        iterator(
+            seq,
+            alt((
+                map(recognize(|s| self.instruction(s)), Some),
+                map(anychar, |_| None),
+            )),
+        )
+        .flatten()
+        .collect()
+    }
 }
 
-/// Try to parse an instruction from the input sequence.
-fn instruction(seq: &str) -> IResult<&str, Instruction> {
-    alt((
-        map(multiply, Instruction::Multiply),
-        map(state_change, Instruction::StateChange),
-    ))
-    .parse(seq)
+/// Lazily evaluate every "mul" instruction, ignoring state changes.
+fn products(instructions: impl IntoIterator<Item = Instruction>) -> impl Iterator<Item = u32> {
+    instructions.into_iter().filter_map(|t| match t {
+        Instruction::Multiply((a, b)) => Some(a * b),
+        _ => None,
+    })
 }
 
-/// Try to parse a corrupted instruction from the input sequence.
-fn corrupted_instruction(seq: &str) -> IResult<&str, Option<Instruction>> {
-    alt((map(instruction, Some), map(anychar, |_| None))).parse(seq)
+/// Lazily evaluate the "mul" instructions which occur while enabled.
+fn enabled_products(
+    instructions: impl IntoIterator<Item = Instruction>,
+) -> impl Iterator<Item = u32> {
+    let mut state = State::Enabled;
+    instructions.into_iter().filter_map(move |t| match t {
+        Instruction::Multiply((a, b)) if state == State::Enabled => Some(a * b),
+        Instruction::StateChange(s) => {
+            state = s;
+            None
+        }
+        _ => None,
+    })
 }
 
 impl InstructionSet {
     fn evaluate(&self) -> Vec<u32> {
-        self.0
-            .iter()
-            .filter_map(|t| match t {
-                Instruction::Multiply((a, b)) => Some(a * b),
-                _ => None,
-            })
-            .collect()
+        products(self.0.iter().copied()).collect()
     }
     fn evaluate_with_state(&self) -> Vec<u32> {
-        let mut state = State::Enabled;
-        self.0
-            .iter()
-            .filter_map(|t| match t {
-                Instruction::Multiply((a, b)) if state == State::Enabled => Some(a * b),
-                Instruction::StateChange(s) => {
-                    state = *s;
-                    None
+        enabled_products(self.0.iter().copied()).collect()
+    }
+}
+
+/// Lazily parse instructions from corrupted memory read in fixed-size chunks.
+///
+/// The parsers are streaming, so an instruction split across a chunk boundary
+/// asks for more input rather than being skipped. Only the unparsed tail of the
+/// input is kept in memory, and each byte is checked as UTF-8 once, when read.
+struct InstructionReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    /// Start of the unparsed input in `buffer`.
+    offset: usize,
+    /// End of the input in `buffer` known to be valid UTF-8.
+    valid: usize,
+    /// Length of the invalid byte sequence at `valid`, if there is one.
+    invalid: Option<usize>,
+    /// Instructions parsed from the buffer but not yet returned.
+    parsed: VecDeque<Instruction>,
+    chunk_size: usize,
+    eof: bool,
+    grammar: Grammar,
+}
+
+impl<R: Read> InstructionReader<R> {
+    fn new(reader: R) -> Self {
+        Self::with_capacity(8 * 1024, reader)
+    }
+
+    fn with_capacity(chunk_size: usize, reader: R) -> Self {
+        assert!(chunk_size > 0, "chunk size must be non-zero");
+        InstructionReader {
+            reader,
+            buffer: Vec::with_capacity(chunk_size),
+            offset: 0,
+            valid: 0,
+            invalid: None,
+            parsed: VecDeque::new(),
+            chunk_size,
+            eof: false,
+            grammar: Grammar::default(),
+        }
+    }
+
+    /// Parse instructions with the given grammar instead of the default.
+    fn with_grammar(mut self, grammar: Grammar) -> Self {
+        self.grammar = grammar;
+        self
+    }
+
+    /// Drop the parsed input and append the next chunk from the reader.
+    fn fill(&mut self) -> io::Result<()> {
+        self.buffer.drain(..self.offset);
+        self.valid -= self.offset;
+        self.offset = 0;
+        let start = self.buffer.len();
+        self.buffer.resize(start + self.chunk_size, 0);
+        let n = loop {
+            match self.reader.read(&mut self.buffer[start..]) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.buffer.truncate(start);
+                    return Err(e);
                 }
-                _ => None,
-            })
-            .collect()
+            }
+        };
+        self.buffer.truncate(start + n);
+        self.eof = n == 0;
+        // Anything after a bad byte is checked once the parser gets past it.
+        if self.invalid.is_none() {
+            self.validate();
+        }
+        Ok(())
+    }
+
+    /// Extend the valid input as far as the buffer allows.
+    ///
+    /// A multi-byte character split across chunks stops the valid input short
+    /// without being invalid, and is checked again when the rest arrives.
+    fn validate(&mut self) {
+        match std::str::from_utf8(&self.buffer[self.valid..]) {
+            Ok(_) => self.valid = self.buffer.len(),
+            Err(e) => {
+                self.valid += e.valid_up_to();
+                self.invalid = e.error_len();
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for InstructionReader<R> {
+    type Item = io::Result<Instruction>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(instruction) = self.parsed.pop_front() {
+                return Some(Ok(instruction));
+            }
+            let seq = std::str::from_utf8(&self.buffer[self.offset..self.valid])
+                .expect("input is validated as it's read");
+            // An instruction cut short by the end of the input or a bad byte
+            // can never be completed, so it's skipped like any other junk.
+            let complete = self.eof || self.invalid.is_some();
+            let mut rest = seq;
+            while let Some(c) = rest.chars().next() {
+                match self.grammar.corrupted_instruction(rest) {
+                    Ok((remaining, instruction)) => {
+                        rest = remaining;
+                        self.parsed.extend(instruction);
+                    }
+                    Err(_) if !complete => break,
+                    Err(_) => rest = &rest[c.len_utf8()..],
+                }
+            }
+            self.offset += seq.len() - rest.len();
+            if !self.parsed.is_empty() {
+                continue;
+            }
+            if let (true, Some(len)) = (self.offset == self.valid, self.invalid) {
+                self.offset += len;
+                self.valid = self.offset;
+                self.invalid = None;
+                self.validate();
+                return Some(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("skipped {} bytes of invalid UTF-8", len),
+                )));
+            }
+            if self.eof {
+                if self.offset == self.buffer.len() {
+                    return None;
+                }
+                self.offset = self.buffer.len();
+                self.valid = self.offset;
+                return Some(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "input ends with an incomplete UTF-8 sequence",
+                )));
+            }
+            if let Err(e) = self.fill() {
+                return Some(Err(e));
+            }
+        }
     }
 }
 
@@ -115,3 +375,122 @@ fn part2() {
             .sum::<u32>()
     )
 }
+
+#[test]
+fn part1_streaming() {
+    let file = std::fs::File::open("input/day03.txt").unwrap();
+    assert_eq!(
+        183380722,
+        products(InstructionReader::new(file).map(Result::unwrap)).sum::<u32>()
+    )
+}
+
+#[test]
+fn part2_streaming() {
+    let file = std::fs::File::open("input/day03.txt").unwrap();
+    assert_eq!(
+        82733683,
+        enabled_products(InstructionReader::new(file).map(Result::unwrap)).sum::<u32>()
+    )
+}
+
+#[test]
+fn streaming_across_chunk_boundaries() {
+    let seq = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+    // Every chunk size splits some instruction, down to one byte at a time.
+    for chunk_size in 1..=seq.len() {
+        let reader = || InstructionReader::with_capacity(chunk_size, seq.as_bytes());
+        assert_eq!(161, products(reader().map(Result::unwrap)).sum::<u32>());
+        assert_eq!(
+            48,
+            enabled_products(reader().map(Result::unwrap)).sum::<u32>()
+        );
+    }
+}
+
+#[test]
+fn streaming_skips_invalid_utf8() {
+    let seq = b"mul(2,3)\xffmul(4,5)\xe2\x82mul(6,7)";
+    for chunk_size in 1..=seq.len() {
+        let results: Vec<_> = InstructionReader::with_capacity(chunk_size, &seq[..])
+            .map(|result| result.map_err(|e| e.kind()))
+            .collect();
+        assert_eq!(
+            vec![
+                Ok(Instruction::Multiply((2, 3))),
+                Err(io::ErrorKind::InvalidData),
+                Ok(Instruction::Multiply((4, 5))),
+                Err(io::ErrorKind::InvalidData),
+                Ok(Instruction::Multiply((6, 7))),
+            ],
+            results,
+            "chunk size {chunk_size}"
+        );
+    }
+    // A character cut off by the end of the input is reported once.
+    let results: Vec<_> = InstructionReader::with_capacity(3, &b"mul(1,1)\xe2\x82"[..])
+        .map(|result| result.map_err(|e| e.kind()))
+        .collect();
+    assert_eq!(
+        vec![
+            Ok(Instruction::Multiply((1, 1))),
+            Err(io::ErrorKind::UnexpectedEof)
+        ],
+        results
+    );
+}
+
+#[test]
+fn grammar_modes() {
+    let whitespace = Grammar {
+        whitespace: true,
+        ..Grammar::default()
+    };
+    let case_insensitive = Grammar {
+        case_sensitive: false,
+        ..Grammar::default()
+    };
+    let modes = [
+        Grammar::default(),
+        Grammar::strict(),
+        whitespace,
+        case_insensitive,
+    ];
+    // token -> accepted by [default, strict, whitespace, case-insensitive]
+    let cases = [
+        ("mul(2,3)", [true, true, true, true]),
+        ("mul(123,4)", [true, true, true, true]),
+        ("mul(1234,5)", [true, false, true, true]),
+        ("mul(4294967296,1)", [false, false, false, false]),
+        ("mul( 2 , 3 )", [false, false, true, false]),
+        ("mul (2,3)", [false, false, true, false]),
+        ("MUL(2,3)", [false, false, false, true]),
+        ("Don't()", [false, false, false, true]),
+        ("do ( )", [false, false, true, false]),
+        ("mul(2,3]", [false, false, false, false]),
+    ];
+    for (token, expected) in cases {
+        for (grammar, accepted) in modes.iter().zip(expected) {
+            assert_eq!(
+                accepted,
+                grammar.tokens(token) == [token],
+                "{token:?} with {grammar:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn strict_grammar_agrees_with_default() {
+    let seq = std::fs::read_to_string("input/day03.txt").unwrap();
+    assert_eq!(
+        Grammar::default().tokens(&seq),
+        Grammar::strict().tokens(&seq)
+    );
+    let file = std::fs::File::open("input/day03.txt").unwrap();
+    let reader = InstructionReader::new(file).with_grammar(Grammar::strict());
+    assert_eq!(
+        82733683,
+        enabled_products(reader.map(Result::unwrap)).sum::<u32>()
+    );
+}
@@ -1,170 +1,441 @@
-#[derive(Debug, Clone, Copy)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-    UpRight,
-    UpLeft,
-    DownRight,
-    DownLeft,
-}
-
-impl Direction {
-    fn all() -> [Direction; 8] {
-        [
-            Direction::Up,
-            Direction::Down,
-            Direction::Left,
-            Direction::Right,
-            Direction::UpRight,
-            Direction::UpLeft,
-            Direction::DownRight,
-            Direction::DownLeft,
-        ]
-    }
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::grid::{Direction, Grid, Position};
+
+/// An Aho-Corasick automaton for finding many words in a single pass.
+#[derive(Debug)]
+struct Dictionary {
+    /// Trie edges out of each node, the root being node 0.
+    children: Vec<HashMap<String, usize>>,
+    /// The node for the longest proper suffix of each node's prefix.
+    fallback: Vec<usize>,
+    /// The words (by index) which end at each node, including via fallbacks.
+    words: Vec<Vec<usize>>,
+    /// The length of each word in cells.
+    lengths: Vec<usize>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-struct Position {
-    pub x: isize,
-    pub y: isize,
-}
-
-impl Position {
-    fn step(&self, distance: isize, direction: Direction) -> Position {
-        match direction {
-            Direction::Up => Position {
-                x: self.x,
-                y: self.y - distance,
-            },
-            Direction::Down => Position {
-                x: self.x,
-                y: self.y + distance,
-            },
-            Direction::Left => Position {
-                x: self.x - distance,
-                y: self.y,
-            },
-            Direction::Right => Position {
-                x: self.x + distance,
-                y: self.y,
-            },
-            Direction::UpRight => Position {
-                x: self.x + distance,
-                y: self.y - distance,
-            },
-            Direction::UpLeft => Position {
-                x: self.x - distance,
-                y: self.y - distance,
-            },
-            Direction::DownRight => Position {
-                x: self.x + distance,
-                y: self.y + distance,
-            },
-            Direction::DownLeft => Position {
-                x: self.x - distance,
-                y: self.y + distance,
-            },
+impl Dictionary {
+    /// Build the automaton from words split into cells, skipping empty words
+    /// since they match nowhere.
+    fn new(words: &[Vec<String>]) -> Self {
+        let mut dictionary = Dictionary {
+            children: vec![HashMap::new()],
+            fallback: vec![0],
+            words: vec![vec![]],
+            lengths: words.iter().map(Vec::len).collect(),
+        };
+        for (i, word) in words.iter().enumerate() {
+            if word.is_empty() {
+                continue;
+            }
+            let mut node = 0;
+            for c in word {
+                node = match dictionary.children[node].get(c) {
+                    Some(&next) => next,
+                    None => {
+                        let next = dictionary.children.len();
+                        dictionary.children.push(HashMap::new());
+                        dictionary.fallback.push(0);
+                        dictionary.words.push(vec![]);
+                        dictionary.children[node].insert(c.clone(), next);
+                        next
+                    }
+                };
+            }
+            dictionary.words[node].push(i);
         }
+        // Breadth-first, so a node's fallback is always resolved before its children.
+        let mut queue: VecDeque<usize> = dictionary.children[0].values().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            let edges: Vec<_> = dictionary.children[node]
+                .iter()
+                .map(|(c, &child)| (c.clone(), child))
+                .collect();
+            for (c, child) in edges {
+                let fallback = dictionary.next(dictionary.fallback[node], &c);
+                dictionary.fallback[child] = fallback;
+                let inherited = dictionary.words[fallback].clone();
+                dictionary.words[child].extend(inherited);
+                queue.push_back(child);
+            }
+        }
+        dictionary
+    }
+
+    /// The node reached by reading the cell `c` from `node`.
+    fn next(&self, mut node: usize, c: &str) -> usize {
+        loop {
+            if let Some(&child) = self.children[node].get(c) {
+                return child;
+            }
+            if node == 0 {
+                return 0;
+            }
+            node = self.fallback[node];
+        }
+    }
+}
+
+/// A word found in the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Match {
+    /// Index of the word in the searched dictionary.
+    word: usize,
+    start: Position,
+    direction: Direction,
+    len: usize,
+}
+
+impl Match {
+    /// The positions covered by the match, from first to last letter.
+    ///
+    /// These run off the grid for matches which wrap around its edges.
+    fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.len).map(|i| self.start.step(i as isize, self.direction))
     }
 }
 
-impl From<(usize, usize)> for Position {
-    fn from((x, y): (usize, usize)) -> Self {
-        Position {
-            x: x as isize,
-            y: y as isize,
+/// How matches may be laid out on the grid.
+#[derive(Debug, Clone, Copy)]
+struct SearchMode {
+    /// Whether words may run off one edge and continue from the opposite one.
+    wrap: bool,
+    /// Whether matches may share cells, otherwise a maximal set of disjoint
+    /// matches is kept, preferring longer words.
+    overlap: bool,
+}
+
+impl Default for SearchMode {
+    fn default() -> Self {
+        SearchMode {
+            wrap: false,
+            overlap: true,
         }
     }
 }
 
+/// A grid of letters, where each cell is a single grapheme cluster.
+///
+/// e.g, "e\u{301}" (an "e" with a combining accent) or "👩‍🔬" fill one cell,
+/// even though they are made of several `char`s.
 #[derive(Debug)]
 struct WordSearch {
-    board: Vec<Vec<char>>,
+    board: Grid<String>,
+    /// Whether letters are compared ignoring case.
+    fold_case: bool,
 }
 
 impl WordSearch {
-    fn get(&self, pos: Position) -> Option<&char> {
-        // Make sure the position is within the grid.
-        if pos.x < 0 || pos.y < 0 {
-            return None;
+    fn with_case_folding(mut self, fold_case: bool) -> Self {
+        self.fold_case = fold_case;
+        self
+    }
+
+    /// The form of a cell (or letter of a word) used for comparisons.
+    fn key<'a>(&self, cell: &'a str) -> Cow<'a, str> {
+        if self.fold_case {
+            Cow::Owned(cell.to_lowercase())
+        } else {
+            Cow::Borrowed(cell)
         }
-        self.board
-            .get(pos.y as usize)
-            .and_then(|row| row.get(pos.x as usize))
     }
 
-    fn coordinates(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
-        self.board
-            .iter()
-            .enumerate()
-            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, _)| (x, y)))
+    /// Split a word into the cells it would occupy, ready for comparison.
+    fn letters(&self, word: &str) -> Vec<String> {
+        word.graphemes(true)
+            .map(|g| self.key(g).into_owned())
+            .collect()
     }
 
     fn matches_in_direction(&self, pos: Position, word: &str, direction: Direction) -> bool {
-        word.chars().enumerate().all(|(i, source)| {
-            match self.get(pos.step(i as isize, direction)) {
-                Some(&target) => source == target,
+        self.letters(word).iter().enumerate().all(|(i, source)| {
+            match self.board.get(pos.step(i as isize, direction)) {
+                Some(target) => *source == self.key(target),
                 None => false,
             }
         })
     }
 
+    /// Find every occurrence of each word, reading in all eight directions.
+    pub fn find_all<S: AsRef<str>>(&self, words: &[S]) -> Vec<Match> {
+        self.find_all_with(words, SearchMode::default())
+    }
+
+    /// Find every occurrence of each word, with the given layout rules.
+    ///
+    /// Each line (or cycle, when wrapping) through the grid is scanned once for
+    /// all words together.
+    pub fn find_all_with<S: AsRef<str>>(&self, words: &[S], mode: SearchMode) -> Vec<Match> {
+        let words: Vec<_> = words.iter().map(|w| self.letters(w.as_ref())).collect();
+        let dictionary = Dictionary::new(&words);
+        let longest = dictionary.lengths.iter().copied().max().unwrap_or(0);
+        let mut matches = Vec::new();
+        for direction in Direction::all() {
+            let tracks: Vec<Vec<Position>> = if mode.wrap {
+                self.board.cycles(direction)
+            } else {
+                self.board
+                    .lines(direction)
+                    .map(|line| line.map(|(pos, _)| pos).collect())
+                    .collect()
+            };
+            for track in tracks {
+                // A cycle is read around once more so words can cross the seam,
+                // but never so far that a word reuses its own cells.
+                let steps = if mode.wrap {
+                    track.len() + longest.min(track.len()) - 1
+                } else {
+                    track.len()
+                };
+                let mut node = 0;
+                for end in 0..steps {
+                    let c = self.board.get(track[end % track.len()]).unwrap();
+                    node = dictionary.next(node, &self.key(c));
+                    for &word in &dictionary.words[node] {
+                        let len = dictionary.lengths[word];
+                        if len > track.len() || end + 1 < len || end + 1 - len >= track.len() {
+                            continue;
+                        }
+                        matches.push(Match {
+                            word,
+                            start: track[end + 1 - len],
+                            direction,
+                            len,
+                        });
+                    }
+                }
+            }
+        }
+        if !mode.overlap {
+            matches = self.disjoint(matches);
+        }
+        matches
+    }
+
+    /// The cells covered by a match, wrapping around the edges if needed.
+    pub fn cells<'a>(&'a self, m: &'a Match) -> impl Iterator<Item = Position> + 'a {
+        m.positions().map(|pos| self.board.wrap(pos))
+    }
+
+    /// Greedily keep matches which don't share cells with any already kept,
+    /// taking longer words first.
+    fn disjoint(&self, mut matches: Vec<Match>) -> Vec<Match> {
+        matches.sort_by_key(|m| std::cmp::Reverse(m.len));
+        let mut used = HashSet::new();
+        matches.retain(|m| {
+            let cells: Vec<_> = self.cells(m).collect();
+            let free = cells.iter().all(|pos| !used.contains(pos));
+            if free {
+                used.extend(cells);
+            }
+            free
+        });
+        matches
+    }
+
     /// Find the word in the grid and return the number of times it appears.
     pub fn count_occurrences<S: AsRef<str>>(&self, word: S) -> usize {
-        self.coordinates()
-            .map(|xy| {
-                Direction::all()
-                    .iter()
-                    .filter(|direction| {
-                        self.matches_in_direction(xy.into(), word.as_ref(), **direction)
-                    })
-                    .count()
+        self.find_all(&[word]).len()
+    }
+
+    /// Find every placement of the stencil, in each of its distinct orientations.
+    ///
+    /// A stencil without letters (e.g from an empty word) matches nowhere.
+    pub fn find_stencil(&self, stencil: &Stencil, symmetry: Symmetry) -> Vec<Placement> {
+        if stencil.letters().next().is_none() {
+            return vec![];
+        }
+        let orientations = stencil.orientations(symmetry);
+        self.board
+            .positions()
+            .flat_map(|origin| {
+                orientations.iter().filter_map(move |stencil| {
+                    let cells: Option<Vec<Position>> = stencil
+                        .letters()
+                        .map(|(offset, letter)| {
+                            let pos = Position {
+                                x: origin.x + offset.x,
+                                y: origin.y + offset.y,
+                            };
+                            let cell = self.board.get(pos)?;
+                            (self.key(cell) == self.key(letter)).then_some(pos)
+                        })
+                        .collect();
+                    cells.map(|cells| Placement { origin, cells })
+                })
             })
-            .sum()
+            .collect()
     }
 
     /// Count the number of times the word apprears in the grid in a cross pattern.
     pub fn count_cross_occurances<S: AsRef<str>>(&self, word: S) -> usize {
-        self.coordinates()
-            .filter(|&xy| {
-                let position = xy.into();
-                let step_size = word.as_ref().chars().count() as isize - 1;
-                let matches: [bool; 4] = [
-                    // upper-left -> lower-right
-                    self.matches_in_direction(position, word.as_ref(), Direction::DownRight),
-                    // upper-right -> lower-left
-                    self.matches_in_direction(
-                        position.step(step_size, Direction::Right),
-                        word.as_ref(),
-                        Direction::DownLeft,
-                    ),
-                    // lower-left -> upper-right
-                    self.matches_in_direction(
-                        position.step(step_size, Direction::Down),
-                        word.as_ref(),
-                        Direction::UpRight,
-                    ),
-                    // lower-right -> upper-left
-                    self.matches_in_direction(
-                        position.step(step_size, Direction::DownRight),
-                        word.as_ref(),
-                        Direction::UpLeft,
-                    ),
-                ];
-                // Whether word appears in exactly two directions.
-                matches.iter().filter(|m| **m).count() == 2
-            })
-            .count()
+        self.find_stencil(&Stencil::cross(word.as_ref()), Symmetry::Rotations)
+            .len()
+    }
+}
+
+/// How to set the matched cells apart when rendering a word search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Highlight {
+    /// Keep every letter, coloring the matched ones with ANSI escapes.
+    Ansi,
+    /// Replace every unmatched letter with '.', as in the puzzle description.
+    Dots,
+}
+
+impl WordSearch {
+    /// Draw the grid as text, highlighting the matched cells.
+    fn render(&self, matched: &HashSet<Position>, highlight: Highlight) -> String {
+        let mut out = String::new();
+        for y in 0..self.board.height() {
+            for x in 0..self.board.width() {
+                let pos = (x, y).into();
+                let c = self.board.get(pos).unwrap();
+                match (highlight, matched.contains(&pos)) {
+                    (Highlight::Ansi, true) => write!(out, "\x1b[1;31m{}\x1b[0m", c).unwrap(),
+                    (Highlight::Dots, false) => out.push('.'),
+                    _ => out.push_str(c),
+                }
+            }
+            out.push('\n');
+        }
+        out
     }
+
+    /// Draw the grid as an SVG image, with the matched cells shaded.
+    fn to_svg(&self, matched: &HashSet<Position>) -> String {
+        const CELL: usize = 20;
+        let mut out = String::new();
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="monospace" font-size="14" text-anchor="middle" dominant-baseline="central">"#,
+            self.board.width() * CELL,
+            self.board.height() * CELL,
+        )
+        .unwrap();
+        for pos in self.board.positions() {
+            let (x, y) = (pos.x as usize * CELL, pos.y as usize * CELL);
+            if matched.contains(&pos) {
+                writeln!(
+                    out,
+                    r#"<rect x="{x}" y="{y}" width="{CELL}" height="{CELL}" fill="gold"/>"#
+                )
+                .unwrap();
+            }
+            let c = self
+                .board
+                .get(pos)
+                .unwrap()
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            writeln!(
+                out,
+                r#"<text x="{}" y="{}">{}</text>"#,
+                x + CELL / 2,
+                y + CELL / 2,
+                c
+            )
+            .unwrap();
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+/// Which orientations of a stencil to search for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Symmetry {
+    /// Only the stencil as written.
+    None,
+    /// The four quarter-turn rotations.
+    Rotations,
+    /// Rotations, and rotations of the mirror image.
+    RotationsAndReflections,
+}
+
+/// A small 2D template of letters, where `None` matches any cell.
+///
+/// e.g, the "X-MAS" shape
+///
+/// ```text
+/// M.S
+/// .A.
+/// M.S
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+struct Stencil(Grid<Option<String>>);
+
+impl Stencil {
+    /// The word along both diagonals of a square, crossing in the middle.
+    fn cross(word: &str) -> Self {
+        let letters: Vec<&str> = word.graphemes(true).collect();
+        let n = letters.len();
+        Stencil(Grid::from_fn(n, n, |pos| {
+            let (x, y) = (pos.x as usize, pos.y as usize);
+            (x == y || x + y + 1 == n).then(|| letters[y].to_string())
+        }))
+    }
+
+    /// The letters of the stencil, as offsets from its top-left corner.
+    fn letters(&self) -> impl Iterator<Item = (Position, &str)> + '_ {
+        self.0
+            .positions()
+            .filter_map(|pos| self.0.get(pos).unwrap().as_deref().map(|c| (pos, c)))
+    }
+
+    /// The distinct orientations of the stencil, so symmetric shapes aren't counted twice.
+    fn orientations(&self, symmetry: Symmetry) -> Vec<Stencil> {
+        let mut bases = vec![self.0.clone()];
+        if symmetry == Symmetry::RotationsAndReflections {
+            bases.push(self.0.transpose());
+        }
+        let mut orientations: Vec<Stencil> = Vec::new();
+        for base in bases {
+            let turns = if symmetry == Symmetry::None { 1 } else { 4 };
+            let rotations = std::iter::successors(Some(base), |g| Some(g.rotate_clockwise()));
+            for grid in rotations.take(turns).map(Stencil) {
+                if !orientations.contains(&grid) {
+                    orientations.push(grid);
+                }
+            }
+        }
+        orientations
+    }
+}
+
+impl std::str::FromStr for Stencil {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s.lines().map(|line| {
+            line.graphemes(true)
+                .map(|c| (c != ".").then(|| c.to_string()))
+        });
+        Ok(Stencil(Grid::from_rows(rows)?))
+    }
+}
+
+/// Where a stencil was found in the grid.
+#[derive(Debug, Clone, PartialEq)]
+struct Placement {
+    /// The top-left corner of the stencil.
+    origin: Position,
+    /// The cells matched by the stencil's letters.
+    cells: Vec<Position>,
 }
 
 impl std::str::FromStr for WordSearch {
-    type Err = ();
+    type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let grid = s.lines().map(|line| line.chars().collect()).collect();
-        Ok(WordSearch { board: grid })
+        Ok(WordSearch {
+            board: Grid::from_rows(s.lines().map(|line| line.graphemes(true).map(String::from)))?,
+            fold_case: false,
+        })
     }
 }
 
@@ -191,3 +462,246 @@ fn part2() {
             .count_cross_occurances("MAS")
     );
 }
+
+#[test]
+fn find_all_locations() {
+    let search: WordSearch = "XMAS\n.A..\n..S.\nSAMX".parse().unwrap();
+    let mut found: Vec<_> = search
+        .find_all(&["XMAS", "AS", "Q"])
+        .iter()
+        .map(|m| {
+            let cells: Vec<_> = m.positions().map(|p| (p.x, p.y)).collect();
+            (m.word, cells)
+        })
+        .collect();
+    found.sort();
+    assert_eq!(
+        vec![
+            (0, vec![(0, 0), (1, 0), (2, 0), (3, 0)]),
+            (0, vec![(3, 3), (2, 3), (1, 3), (0, 3)]),
+            (1, vec![(1, 1), (2, 2)]),
+            (1, vec![(1, 3), (0, 3)]),
+            (1, vec![(1, 3), (2, 2)]),
+            (1, vec![(2, 0), (3, 0)]),
+        ],
+        found
+    );
+}
+
+#[test]
+fn find_all_agrees_with_brute_force() {
+    let search = std::fs::read_to_string("input/day04.txt")
+        .unwrap()
+        .parse::<WordSearch>()
+        .unwrap();
+    let words = ["XMAS", "MAS", "AS", "SAMX"];
+    let matches = search.find_all(&words);
+    for (i, word) in words.iter().enumerate() {
+        let expected: usize = search
+            .board
+            .positions()
+            .map(|position| {
+                Direction::all()
+                    .into_iter()
+                    .filter(|&direction| search.matches_in_direction(position, word, direction))
+                    .count()
+            })
+            .sum();
+        assert_eq!(expected, matches.iter().filter(|m| m.word == i).count());
+    }
+}
+
+#[test]
+fn stencil_orientations() {
+    let x: Stencil = "M.S\n.A.\nM.S".parse().unwrap();
+    assert!(Stencil::cross("MAS")
+        .orientations(Symmetry::Rotations)
+        .contains(&x));
+    assert_eq!(4, x.orientations(Symmetry::Rotations).len());
+    // Mirroring the X-MAS only gives shapes which are already rotations.
+    assert_eq!(4, x.orientations(Symmetry::RotationsAndReflections).len());
+
+    let plus: Stencil = ".X.\nXXX\n.X.".parse().unwrap();
+    assert_eq!(
+        1,
+        plus.orientations(Symmetry::RotationsAndReflections).len()
+    );
+
+    let ell: Stencil = "X.\nX.\nXX".parse().unwrap();
+    assert_eq!(1, ell.orientations(Symmetry::None).len());
+    assert_eq!(4, ell.orientations(Symmetry::Rotations).len());
+    assert_eq!(8, ell.orientations(Symmetry::RotationsAndReflections).len());
+}
+
+#[test]
+fn find_plus_stencil() {
+    let search: WordSearch = "XMAS\nMASA\nSSAM\nXAMX".parse().unwrap();
+    let plus: Stencil = ".M.\nMAS\n.S.".parse().unwrap();
+    let found = search.find_stencil(&plus, Symmetry::None);
+    assert_eq!(
+        vec![Placement {
+            origin: (0, 0).into(),
+            cells: [(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)]
+                .into_iter()
+                .map(Position::from)
+                .collect(),
+        }],
+        found
+    );
+    assert_eq!(2, search.find_stencil(&plus, Symmetry::Rotations).len());
+}
+
+#[test]
+fn empty_stencils_match_nowhere() {
+    let search: WordSearch = "XMAS\nMASA".parse().unwrap();
+    assert_eq!(0, search.count_cross_occurances(""));
+    let blank: Stencil = "..\n..".parse().unwrap();
+    assert!(search.find_stencil(&blank, Symmetry::None).is_empty());
+}
+
+const EXAMPLE: &str = "\
+MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX
+";
+
+#[test]
+fn render_straight_matches() {
+    let search: WordSearch = EXAMPLE.parse().unwrap();
+    let matches = search.find_all(&["XMAS"]);
+    assert_eq!(18, matches.len());
+    let matched = matches.iter().flat_map(Match::positions).collect();
+    assert_eq!(
+        "\
+....XXMAS.
+.SAMXMS...
+...S..A...
+..A.A.MS.X
+XMASAMX.MM
+X.....XA.A
+S.S.S.S.SS
+.A.A.A.A.A
+..M.M.M.MM
+.X.X.XMASX
+",
+        search.render(&matched, Highlight::Dots)
+    );
+}
+
+#[test]
+fn render_cross_matches() {
+    let search: WordSearch = EXAMPLE.parse().unwrap();
+    let placements = search.find_stencil(&Stencil::cross("MAS"), Symmetry::Rotations);
+    assert_eq!(9, placements.len());
+    let matched = placements.into_iter().flat_map(|p| p.cells).collect();
+    assert_eq!(
+        "\
+.M.S......
+..A..MSMS.
+.M.S.MAA..
+..A.ASMSM.
+.M.S.M....
+..........
+S.S.S.S.S.
+.A.A.A.A..
+M.M.M.M.M.
+..........
+",
+        search.render(&matched, Highlight::Dots)
+    );
+}
+
+#[test]
+fn render_ansi_and_svg() {
+    let search: WordSearch = "XM\nA<".parse().unwrap();
+    let matched = HashSet::from([Position { x: 1, y: 0 }]);
+    assert_eq!(
+        "X\x1b[1;31mM\x1b[0m\nA<\n",
+        search.render(&matched, Highlight::Ansi)
+    );
+    let svg = search.to_svg(&matched);
+    assert!(svg.starts_with("<svg"));
+    assert_eq!(1, svg.matches("<rect").count());
+    assert_eq!(4, svg.matches("<text").count());
+    assert!(svg.contains(r#"<rect x="20" y="0""#));
+    assert!(svg.contains("&lt;</text>"));
+}
+
+#[test]
+fn wrapping_search() {
+    let search: WordSearch = "ASXM\n....\n....\n....".parse().unwrap();
+    let wrap = SearchMode {
+        wrap: true,
+        ..SearchMode::default()
+    };
+    assert!(search.find_all(&["XMAS"]).is_empty());
+    let matches = search.find_all_with(&["XMAS"], wrap);
+    assert_eq!(1, matches.len());
+    let cells: Vec<_> = search.cells(&matches[0]).map(|p| (p.x, p.y)).collect();
+    assert_eq!(vec![(2, 0), (3, 0), (0, 0), (1, 0)], cells);
+
+    // Words longer than a cycle would have to reuse their own cells.
+    let search: WordSearch = "XMA\n...\n...".parse().unwrap();
+    assert!(search.find_all_with(&["XMAX"], wrap).is_empty());
+    assert_eq!(1, search.find_all_with(&["AXM"], wrap).len());
+}
+
+#[test]
+fn disjoint_search() {
+    let search: WordSearch = "XMASAMX".parse().unwrap();
+    let disjoint = SearchMode {
+        overlap: false,
+        ..SearchMode::default()
+    };
+    assert_eq!(6, search.find_all(&["XMAS", "SAM", "AM"]).len());
+    let matches = search.find_all_with(&["XMAS", "SAM", "AM"], disjoint);
+    let words: Vec<_> = matches.iter().map(|m| m.word).collect();
+    assert_eq!(vec![0, 2], words);
+}
+
+#[test]
+fn grapheme_cells() {
+    // "É" is written as "E" and a combining accent, so spans two `char`s.
+    let search: WordSearch = "CAFE\u{301}\nx🎄y👩‍🔬\nz🎁🎄e\u{301}".parse().unwrap();
+    assert_eq!((4, 3), (search.board.width(), search.board.height()));
+    assert_eq!(1, search.count_occurrences("CAFE\u{301}"));
+    assert_eq!(0, search.count_occurrences("CAFE"));
+    // Read forwards and backwards along the same diagonal.
+    assert_eq!(2, search.count_occurrences("🎄🎄"));
+    assert_eq!(1, search.count_occurrences("A🎄"));
+    assert_eq!(
+        "....\n.🎄..\n..🎄.\n",
+        search.render(
+            &search
+                .find_all(&["🎄🎄"])
+                .iter()
+                .flat_map(Match::positions)
+                .collect(),
+            Highlight::Dots
+        )
+    );
+
+    assert_eq!(1, search.count_occurrences("👩‍🔬E\u{301}"));
+    let search = search.with_case_folding(true);
+    assert_eq!(1, search.count_occurrences("cafe\u{301}"));
+    assert_eq!(2, search.count_occurrences("👩‍🔬E\u{301}"));
+}
+
+#[test]
+fn grapheme_cross() {
+    let search: WordSearch = "ñ.ß\n.👍🏽.\nÑ.ẞ".parse().unwrap();
+    assert_eq!(0, search.count_cross_occurances("ñ👍🏽ß"));
+    assert_eq!(
+        1,
+        search
+            .with_case_folding(true)
+            .count_cross_occurances("ñ👍🏽ß")
+    );
+}
@@ -1,29 +1,296 @@
-use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::str::FromStr;
 
+/// The rules of a print queue, generic over how pages are labelled.
+///
+/// e.g, `RuleSet<u32>` for page numbers or `RuleSet<String>` for names.
 #[derive(Debug)]
-pub struct RuleSet {
-    inner: HashMap<u8, HashSet<u8>>,
+pub struct RuleSet<P> {
+    inner: HashMap<P, HashSet<P>>,
 }
 
-impl RuleSet {
-    fn sorted(&self, manual: &[u8]) -> Vec<u8> {
-        let mut sorted = manual.to_vec();
-        sorted.sort_by(|a, b| {
-            self.inner
-                .get(a)
-                .and_then(|inner| inner.get(b))
-                .map(|_| Ordering::Less)
-                .unwrap_or(Ordering::Greater)
-        });
-        sorted
+/// Why the pages of a manual can't be put in a single order.
+#[derive(Debug, PartialEq)]
+pub enum OrderError<P> {
+    /// The rules between these pages form a cycle, e.g [a, b, c] for the
+    /// rules a|b, b|c and c|a.
+    Cycle(Vec<P>),
+    /// No rule decides which of these two pages comes first.
+    Ambiguous(P, P),
+}
+
+impl<P: Display> Display for OrderError<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderError::Cycle(pages) => {
+                let pages: Vec<_> = pages.iter().map(P::to_string).collect();
+                write!(f, "rules form a cycle: {}", pages.join(" -> "))
+            }
+            OrderError::Ambiguous(a, b) => write!(f, "no rule orders pages {} and {}", a, b),
+        }
     }
 }
 
-impl std::str::FromStr for RuleSet {
+impl<P: Debug + Display> std::error::Error for OrderError<P> {}
+
+/// A rule a|b broken by a manual, because b comes before a.
+#[derive(Debug, PartialEq)]
+pub struct Violation<P> {
+    /// The pages (a, b) of the broken rule.
+    pub rule: (P, P),
+    /// Where a and b appear in the manual.
+    pub positions: (usize, usize),
+}
+
+/// Take a page out of a manual and insert it somewhere else.
+#[derive(Debug, PartialEq)]
+pub struct Move<P> {
+    pub page: P,
+    /// Where the page was, before moving it.
+    pub from: usize,
+    /// Where the page is, after moving it.
+    pub to: usize,
+}
+
+impl<P: Eq + Hash + Clone> RuleSet<P> {
+    /// Whether a rule says page `a` must come before page `b`.
+    fn precedes(&self, a: &P, b: &P) -> bool {
+        self.inner.get(a).is_some_and(|after| after.contains(b))
+    }
+
+    /// Topologically sort the pages of a manual, using only the rules between them.
+    ///
+    /// Fails unless the rules determine exactly one order.
+    fn sorted(&self, manual: &[P]) -> Result<Vec<P>, OrderError<P>> {
+        // Pages are nodes by index, so repeated pages are kept.
+        let successors: Vec<Vec<usize>> = manual
+            .iter()
+            .map(|a| {
+                (0..manual.len())
+                    .filter(|&j| self.precedes(a, &manual[j]))
+                    .collect()
+            })
+            .collect();
+        let mut in_degree = vec![0; manual.len()];
+        for j in successors.iter().flatten() {
+            in_degree[*j] += 1;
+        }
+        let mut ready: Vec<usize> = (0..manual.len()).filter(|&i| in_degree[i] == 0).collect();
+        let mut sorted = Vec::with_capacity(manual.len());
+        while let Some(&i) = ready.first() {
+            if let Some(&j) = ready.iter().find(|&&j| manual[j] != manual[i]) {
+                return Err(OrderError::Ambiguous(manual[i].clone(), manual[j].clone()));
+            }
+            // Copies of one page are interchangeable, so the order is still unique.
+            for i in std::mem::take(&mut ready) {
+                sorted.push(manual[i].clone());
+                for &j in &successors[i] {
+                    in_degree[j] -= 1;
+                    if in_degree[j] == 0 {
+                        ready.push(j);
+                    }
+                }
+            }
+            ready.sort_unstable();
+        }
+        if sorted.len() < manual.len() {
+            return Err(OrderError::Cycle(self.cycle(manual, &in_degree)));
+        }
+        Ok(sorted)
+    }
+
+    /// Whether the manual breaks no rules, checked over all pairs without
+    /// sorting.
+    ///
+    /// Each page's rules are looked up against the first position of every
+    /// page, so this takes O(n + r) time for `n` pages and the `r` rules
+    /// starting from them. Repeated pages are checked at every position they
+    /// appear.
+    fn is_ordered(&self, manual: &[P]) -> bool {
+        let mut first = HashMap::with_capacity(manual.len());
+        for (i, page) in manual.iter().enumerate() {
+            first.entry(page).or_insert(i);
+        }
+        manual.iter().enumerate().all(|(i, page)| {
+            // A page is out of order if it must come before an earlier one.
+            self.inner.get(page).is_none_or(|after| {
+                after
+                    .iter()
+                    .all(|next| first.get(next).is_none_or(|&j| j >= i))
+            })
+        })
+    }
+
+    /// Every rule broken by the manual.
+    fn violations(&self, manual: &[P]) -> Vec<Violation<P>> {
+        let mut violations = Vec::new();
+        for (i, b) in manual.iter().enumerate() {
+            for (j, a) in manual.iter().enumerate().skip(i + 1) {
+                if self.precedes(a, b) {
+                    violations.push(Violation {
+                        rule: (a.clone(), b.clone()),
+                        positions: (j, i),
+                    });
+                }
+            }
+        }
+        violations
+    }
+
+    /// The fewest moves which put the manual in order, applied one after another.
+    ///
+    /// The pages in the longest run (not necessarily contiguous) which is
+    /// already in order stay put, and every other page is moved next to the
+    /// page which should precede it.
+    fn moves_to_fix(&self, manual: &[P]) -> Result<Vec<Move<P>>, OrderError<P>> {
+        let sorted = self.sorted(manual)?;
+        let rank: HashMap<&P, usize> = sorted.iter().enumerate().map(|(r, p)| (p, r)).collect();
+        let ranks: Vec<usize> = manual.iter().map(|p| rank[p]).collect();
+
+        // Longest increasing subsequence of ranks, tracking each best predecessor.
+        let mut length = vec![1; ranks.len()];
+        let mut previous = vec![None; ranks.len()];
+        for j in 0..ranks.len() {
+            for i in 0..j {
+                if ranks[i] < ranks[j] && length[i] + 1 > length[j] {
+                    length[j] = length[i] + 1;
+                    previous[j] = Some(i);
+                }
+            }
+        }
+        let mut in_place = vec![false; sorted.len()];
+        let mut end = (0..ranks.len()).max_by_key(|&i| length[i]);
+        while let Some(i) = end {
+            in_place[ranks[i]] = true;
+            end = previous[i];
+        }
+
+        let mut current = manual.to_vec();
+        let mut moves = Vec::new();
+        for (r, page) in sorted.iter().enumerate() {
+            if in_place[r] {
+                continue;
+            }
+            let from = current.iter().position(|p| p == page).unwrap();
+            current.remove(from);
+            let to = match r {
+                0 => 0,
+                _ => current.iter().position(|p| p == &sorted[r - 1]).unwrap() + 1,
+            };
+            current.insert(to, page.clone());
+            moves.push(Move {
+                page: page.clone(),
+                from,
+                to,
+            });
+        }
+        Ok(moves)
+    }
+
+    /// Only the rules between the given pages, e.g the pages of one manual.
+    fn restricted(&self, pages: &[P]) -> RuleSet<P> {
+        let pages: HashSet<&P> = pages.iter().collect();
+        let inner = self
+            .inner
+            .iter()
+            .filter(|(a, _)| pages.contains(a))
+            .map(|(a, after)| {
+                let after = after
+                    .iter()
+                    .filter(|b| pages.contains(b))
+                    .cloned()
+                    .collect();
+                (a.clone(), after)
+            })
+            .collect();
+        RuleSet { inner }
+    }
+
+    /// Every page reachable from `a` by following one or more rules.
+    fn reachable(&self, a: &P) -> HashSet<P> {
+        let mut reached = HashSet::new();
+        let mut stack: Vec<&P> = vec![a];
+        while let Some(page) = stack.pop() {
+            for next in self.inner.get(page).into_iter().flatten() {
+                if reached.insert(next.clone()) {
+                    stack.push(next);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Whether the rules imply `a` must come before `b`, directly or through
+    /// a chain of other pages.
+    fn must_precede(&self, a: &P, b: &P) -> bool {
+        self.reachable(a).contains(b)
+    }
+
+    /// The transitive closure, with a rule a|b whenever a must precede b.
+    ///
+    /// Sorting with the closure orders pages whose rule is only implied by
+    /// pages missing from the manual.
+    fn closure(&self) -> RuleSet<P> {
+        let inner = self
+            .inner
+            .keys()
+            .map(|a| (a.clone(), self.reachable(a)))
+            .collect();
+        RuleSet { inner }
+    }
+
+    /// Find a cycle among the pages a topological sort couldn't place.
+    fn cycle(&self, manual: &[P], in_degree: &[usize]) -> Vec<P> {
+        let remaining: Vec<usize> = (0..manual.len()).filter(|&i| in_degree[i] > 0).collect();
+        // Every remaining page has a remaining predecessor, so walking
+        // backwards must eventually revisit a page.
+        let mut path = vec![remaining[0]];
+        loop {
+            let current = *path.last().unwrap();
+            let previous = *remaining
+                .iter()
+                .find(|&&i| self.precedes(&manual[i], &manual[current]))
+                .unwrap();
+            if let Some(start) = path.iter().position(|&i| i == previous) {
+                return path[start..]
+                    .iter()
+                    .rev()
+                    .map(|&i| manual[i].clone())
+                    .collect();
+            }
+            path.push(previous);
+        }
+    }
+}
+
+impl<P: Display> RuleSet<P> {
+    /// The rules as a Graphviz DOT graph, with an edge a -> b for each rule a|b.
+    fn to_dot(&self) -> String {
+        let quote = |page: &P| format!("{:?}", page.to_string());
+        let mut edges: Vec<String> = self
+            .inner
+            .iter()
+            .flat_map(|(a, after)| {
+                after
+                    .iter()
+                    .map(move |b| format!("    {} -> {};\n", quote(a), quote(b)))
+            })
+            .collect();
+        edges.sort();
+        format!("digraph rules {{\n{}}}\n", edges.concat())
+    }
+}
+
+impl<P> FromStr for RuleSet<P>
+where
+    P: Eq + Hash + FromStr,
+    P::Err: std::error::Error + Send + Sync + 'static,
+{
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut rules = HashMap::<u8, HashSet<u8>>::new();
+        let mut rules = HashMap::<P, HashSet<P>>::new();
         for line in s.lines() {
             let (a, b) = line
                 .split_once('|')
@@ -35,12 +302,16 @@ impl std::str::FromStr for RuleSet {
 }
 
 #[derive(Debug)]
-pub struct Puzzle {
-    pub ruleset: RuleSet,
-    pub manuals: Vec<Vec<u8>>,
+pub struct Puzzle<P> {
+    pub ruleset: RuleSet<P>,
+    pub manuals: Vec<Vec<P>>,
 }
 
-impl std::str::FromStr for Puzzle {
+impl<P> FromStr for Puzzle<P>
+where
+    P: Eq + Hash + FromStr,
+    P::Err: std::error::Error + Send + Sync + 'static,
+{
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -61,23 +332,18 @@ impl std::str::FromStr for Puzzle {
     }
 }
 
-fn is_same_sequence(a: &[u8], b: &[u8]) -> bool {
-    a.iter().zip(b.iter()).all(|(a, b)| a == b)
-}
-
 #[test]
 fn part1() {
     let Puzzle { ruleset, manuals } = std::fs::read_to_string("input/day05.txt")
         .unwrap()
-        .parse::<Puzzle>()
+        .parse::<Puzzle<u32>>()
         .unwrap();
     assert_eq!(
         4185,
         manuals
             .iter()
-            .map(|manual| (manual, ruleset.sorted(manual)))
-            .filter(|(manual, sorted)| is_same_sequence(manual, sorted))
-            .map(|(_, sorted)| sorted[sorted.len() / 2] as usize)
+            .filter(|manual| ruleset.is_ordered(manual))
+            .map(|manual| manual[manual.len() / 2] as usize)
             .sum::<usize>()
     );
 }
@@ -86,15 +352,202 @@ fn part1() {
 fn part2() {
     let Puzzle { ruleset, manuals } = std::fs::read_to_string("input/day05.txt")
         .unwrap()
-        .parse::<Puzzle>()
+        .parse::<Puzzle<u32>>()
         .unwrap();
     assert_eq!(
         4480,
         manuals
             .iter()
-            .map(|manual| (manual, ruleset.sorted(manual)))
-            .filter(|(manual, sorted)| !is_same_sequence(manual, sorted))
-            .map(|(_, sorted)| sorted[sorted.len() / 2] as usize)
+            .filter(|manual| !ruleset.is_ordered(manual))
+            .map(|manual| ruleset.sorted(manual).unwrap())
+            .map(|sorted| sorted[sorted.len() / 2] as usize)
             .sum::<usize>()
     );
 }
+
+#[test]
+fn sort_errors() {
+    let ruleset: RuleSet<u32> = "1|2\n2|3\n3|1\n4|5".parse().unwrap();
+    assert_eq!(Ok(vec![4, 5]), ruleset.sorted(&[5, 4]));
+    assert_eq!(
+        Err(OrderError::Cycle(vec![3, 1, 2])),
+        ruleset.sorted(&[4, 2, 1, 3])
+    );
+    assert_eq!(Err(OrderError::Ambiguous(4, 1)), ruleset.sorted(&[4, 1, 2]));
+    assert_eq!(Ok(vec![4, 4, 5]), ruleset.sorted(&[4, 5, 4]));
+    assert_eq!(Err(OrderError::Ambiguous(4, 1)), ruleset.sorted(&[4, 4, 1]));
+}
+
+#[test]
+fn sort_repeated_pages() {
+    let Puzzle { ruleset, .. } = EXAMPLE.parse::<Puzzle<u32>>().unwrap();
+    assert!(ruleset.is_ordered(&[75, 75]));
+    assert_eq!(Ok(vec![75, 75]), ruleset.sorted(&[75, 75]));
+    assert!(!ruleset.is_ordered(&[47, 75, 75]));
+    assert_eq!(Ok(vec![75, 75, 47]), ruleset.sorted(&[47, 75, 75]));
+}
+
+const EXAMPLE: &str = "\
+47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
+
+75,47,61,53,29
+97,61,53,29,13
+75,29,13
+75,97,47,61,53
+61,13,29
+97,13,75,29,47
+";
+
+#[test]
+fn violations_and_moves() {
+    let Puzzle { ruleset, manuals } = EXAMPLE.parse::<Puzzle<u32>>().unwrap();
+    assert!(ruleset.violations(&manuals[0]).is_empty());
+    assert!(ruleset.moves_to_fix(&manuals[0]).unwrap().is_empty());
+
+    assert_eq!(
+        vec![Violation {
+            rule: (97, 75),
+            positions: (1, 0)
+        }],
+        ruleset.violations(&manuals[3])
+    );
+    assert_eq!(
+        vec![Move {
+            page: 97,
+            from: 1,
+            to: 0
+        }],
+        ruleset.moves_to_fix(&manuals[3]).unwrap()
+    );
+
+    let broken: Vec<_> = ruleset
+        .violations(&manuals[5])
+        .into_iter()
+        .map(|v| v.rule)
+        .collect();
+    assert_eq!(vec![(75, 13), (29, 13), (47, 13), (47, 29)], broken);
+    assert_eq!(2, ruleset.moves_to_fix(&manuals[5]).unwrap().len());
+}
+
+#[test]
+fn moves_fix_every_manual() {
+    let Puzzle { ruleset, manuals } = std::fs::read_to_string("input/day05.txt")
+        .unwrap()
+        .parse::<Puzzle<u32>>()
+        .unwrap();
+    for manual in manuals {
+        let sorted = ruleset.sorted(&manual).unwrap();
+        let moves = ruleset.moves_to_fix(&manual).unwrap();
+        assert_eq!(sorted == manual, ruleset.is_ordered(&manual));
+        assert_eq!(sorted == manual, ruleset.violations(&manual).is_empty());
+        assert_eq!(sorted == manual, moves.is_empty());
+        let mut fixed = manual.clone();
+        for Move { page, from, to } in moves {
+            assert_eq!(page, fixed.remove(from));
+            fixed.insert(to, page);
+        }
+        assert_eq!(sorted, fixed);
+    }
+}
+
+#[test]
+fn ordering_checks() {
+    let Puzzle { ruleset, manuals } = EXAMPLE.parse::<Puzzle<u32>>().unwrap();
+    let ordered: Vec<_> = manuals.iter().map(|m| ruleset.is_ordered(m)).collect();
+    assert_eq!(vec![true, true, true, false, false, false], ordered);
+    assert!(ruleset.is_ordered(&[]));
+    assert!(ruleset.is_ordered(&[75, 75]));
+    assert!(ruleset.is_ordered(&[75, 47]));
+    // The second 75 comes after 47, breaking 75|47.
+    assert!(!ruleset.is_ordered(&[75, 47, 75]));
+    // A prefix of an ordered manual is ordered, but sharing that prefix
+    // doesn't make a longer manual ordered.
+    let prefix = &manuals[0][..3];
+    assert!(ruleset.is_ordered(prefix));
+    let mut broken = manuals[0].clone();
+    broken.swap(3, 4);
+    assert_eq!(prefix, &broken[..3]);
+    assert!(!ruleset.is_ordered(&broken));
+    assert_ne!(broken, ruleset.sorted(&broken).unwrap());
+}
+
+#[test]
+fn wide_and_labelled_pages() {
+    assert!("300|1\n\n1,300".parse::<Puzzle<u8>>().is_err());
+    let Puzzle { ruleset, manuals } = "300|1\n\n1,300".parse::<Puzzle<u16>>().unwrap();
+    assert_eq!(vec![300, 1], ruleset.sorted(&manuals[0]).unwrap());
+
+    let Puzzle { ruleset, manuals } = "intro|body\nbody|index\nintro|index\n\nbody,intro,index"
+        .parse::<Puzzle<String>>()
+        .unwrap();
+    assert!(!ruleset.is_ordered(&manuals[0]));
+    assert_eq!(
+        vec!["intro", "body", "index"],
+        ruleset.sorted(&manuals[0]).unwrap()
+    );
+    assert_eq!(
+        "no rule orders pages body and appendix",
+        ruleset
+            .sorted(&["body".to_string(), "appendix".to_string()])
+            .unwrap_err()
+            .to_string()
+    );
+}
+
+#[test]
+fn transitive_rules() {
+    let ruleset: RuleSet<u32> = "1|2\n2|3\n4|3".parse().unwrap();
+    assert!(ruleset.must_precede(&1, &3));
+    assert!(!ruleset.must_precede(&3, &1));
+    assert!(!ruleset.must_precede(&1, &4));
+    // Without page 2 only the closure knows 1 comes before 3.
+    assert_eq!(Err(OrderError::Ambiguous(3, 1)), ruleset.sorted(&[3, 1]));
+    assert_eq!(Ok(vec![1, 3]), ruleset.closure().sorted(&[3, 1]));
+    assert!(!ruleset.restricted(&[1, 3]).must_precede(&1, &3));
+
+    let Puzzle { ruleset, manuals } = EXAMPLE.parse::<Puzzle<u32>>().unwrap();
+    let closure = ruleset.closure();
+    for manual in &manuals {
+        assert_eq!(ruleset.sorted(manual), closure.sorted(manual));
+    }
+}
+
+#[test]
+fn rules_as_dot() {
+    let ruleset: RuleSet<String> = "a|b\nb|c\na|c\nc|d".parse().unwrap();
+    assert_eq!(
+        "\
+digraph rules {
+    \"a\" -> \"b\";
+    \"a\" -> \"c\";
+    \"b\" -> \"c\";
+    \"c\" -> \"d\";
+}
+",
+        ruleset.to_dot()
+    );
+    assert_eq!(
+        "digraph rules {\n    \"a\" -> \"c\";\n}\n",
+        ruleset.restricted(&["c".into(), "a".into()]).to_dot()
+    );
+}
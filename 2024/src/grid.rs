@@ -0,0 +1,359 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpRight,
+    UpLeft,
+    DownRight,
+    DownLeft,
+}
+
+impl Direction {
+    /// Every direction, including diagonals.
+    pub fn all() -> [Direction; 8] {
+        [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+            Direction::UpRight,
+            Direction::UpLeft,
+            Direction::DownRight,
+            Direction::DownLeft,
+        ]
+    }
+
+    /// The four directions which share an edge with a cell.
+    pub fn orthogonal() -> [Direction; 4] {
+        [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+    }
+
+    /// The change in (x, y) of a single step in this direction.
+    pub fn delta(&self) -> (isize, isize) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::UpRight => (1, -1),
+            Direction::UpLeft => (-1, -1),
+            Direction::DownRight => (1, 1),
+            Direction::DownLeft => (-1, 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub x: isize,
+    pub y: isize,
+}
+
+impl Position {
+    pub fn step(&self, distance: isize, direction: Direction) -> Position {
+        let (dx, dy) = direction.delta();
+        Position {
+            x: self.x + dx * distance,
+            y: self.y + dy * distance,
+        }
+    }
+}
+
+impl From<(usize, usize)> for Position {
+    fn from((x, y): (usize, usize)) -> Self {
+        Position {
+            x: x as isize,
+            y: y as isize,
+        }
+    }
+}
+
+/// A rectangular grid of cells, stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid<T> {
+    cells: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T> Grid<T> {
+    /// Build a grid from its rows, which must all be the same length.
+    pub fn from_rows<R>(rows: impl IntoIterator<Item = R>) -> anyhow::Result<Self>
+    where
+        R: IntoIterator<Item = T>,
+    {
+        let mut cells = Vec::new();
+        let mut width = None;
+        let mut height = 0;
+        for row in rows {
+            let start = cells.len();
+            cells.extend(row);
+            let len = cells.len() - start;
+            if *width.get_or_insert(len) != len {
+                anyhow::bail!("Invalid grid, row {} has {} cells", height, len);
+            }
+            height += 1;
+        }
+        Ok(Grid {
+            cells,
+            width: width.unwrap_or(0),
+            height,
+        })
+    }
+
+    /// Build a grid of the given size, computing each cell from its position.
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(Position) -> T) -> Self {
+        Grid {
+            cells: (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y).into()))
+                .map(&mut f)
+                .collect(),
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, pos: Position) -> Option<usize> {
+        // Make sure the position is within the grid.
+        if pos.x < 0 || pos.y < 0 || pos.x as usize >= self.width || pos.y as usize >= self.height {
+            return None;
+        }
+        Some(pos.y as usize * self.width + pos.x as usize)
+    }
+
+    pub fn get(&self, pos: Position) -> Option<&T> {
+        self.index(pos).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, pos: Position) -> Option<&mut T> {
+        self.index(pos).map(|i| &mut self.cells[i])
+    }
+
+    /// The position on the grid when its edges wrap around, as on a torus.
+    pub fn wrap(&self, pos: Position) -> Position {
+        Position {
+            x: pos.x.rem_euclid(self.width.max(1) as isize),
+            y: pos.y.rem_euclid(self.height.max(1) as isize),
+        }
+    }
+
+    /// Every position in the grid, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| (x, y).into()))
+    }
+
+    /// The in-bounds cells one step away from a position in each direction.
+    pub fn neighbors(
+        &self,
+        pos: Position,
+        directions: impl IntoIterator<Item = Direction>,
+    ) -> impl Iterator<Item = (Position, &T)> {
+        directions.into_iter().filter_map(move |direction| {
+            let next = pos.step(1, direction);
+            self.get(next).map(|cell| (next, cell))
+        })
+    }
+
+    /// Walk from a position in one direction until leaving the grid.
+    pub fn line(
+        &self,
+        start: Position,
+        direction: Direction,
+    ) -> impl Iterator<Item = (Position, &T)> {
+        (0..).map_while(move |i| {
+            let pos = start.step(i, direction);
+            self.get(pos).map(|cell| (pos, cell))
+        })
+    }
+
+    /// Every maximal line through the grid in one direction.
+    ///
+    /// e.g, `Direction::Right` yields the rows, `Direction::Down` the columns
+    /// and `Direction::DownRight` the diagonals.
+    pub fn lines(
+        &self,
+        direction: Direction,
+    ) -> impl Iterator<Item = impl Iterator<Item = (Position, &T)>> {
+        self.positions()
+            .filter(move |pos| self.get(pos.step(-1, direction)).is_none())
+            .map(move |start| self.line(start, direction))
+    }
+
+    /// Every cycle through the grid in one direction when its edges wrap around.
+    ///
+    /// Each position belongs to exactly one cycle.
+    pub fn cycles(&self, direction: Direction) -> Vec<Vec<Position>> {
+        let mut visited = vec![false; self.cells.len()];
+        let mut cycles = Vec::new();
+        for start in self.positions() {
+            let mut cycle = Vec::new();
+            let mut pos = start;
+            while let Some(i) = self.index(pos).filter(|&i| !visited[i]) {
+                visited[i] = true;
+                cycle.push(pos);
+                pos = self.wrap(pos.step(1, direction));
+            }
+            if !cycle.is_empty() {
+                cycles.push(cycle);
+            }
+        }
+        cycles
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        (0..self.height).map(|y| &self.cells[y * self.width..(y + 1) * self.width])
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).map(|x| self.cells.iter().skip(x).step_by(self.width))
+    }
+
+    pub fn diagonals(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        self.lines(Direction::DownRight)
+            .map(|line| line.map(|(_, cell)| cell))
+    }
+
+    pub fn anti_diagonals(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        self.lines(Direction::DownLeft)
+            .map(|line| line.map(|(_, cell)| cell))
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    /// Build a new grid of the given size, taking each cell from this one.
+    fn remap(&self, width: usize, height: usize, source: impl Fn(usize, usize) -> usize) -> Self {
+        Grid::from_fn(width, height, |pos| {
+            self.cells[source(pos.x as usize, pos.y as usize)].clone()
+        })
+    }
+
+    /// Swap rows and columns.
+    pub fn transpose(&self) -> Self {
+        self.remap(self.height, self.width, |x, y| x * self.width + y)
+    }
+
+    /// Rotate a quarter turn clockwise.
+    pub fn rotate_clockwise(&self) -> Self {
+        self.remap(self.height, self.width, |x, y| {
+            (self.height - 1 - x) * self.width + y
+        })
+    }
+
+    /// Rotate a quarter turn counter-clockwise.
+    pub fn rotate_counter_clockwise(&self) -> Self {
+        self.remap(self.height, self.width, |x, y| {
+            x * self.width + (self.width - 1 - y)
+        })
+    }
+}
+
+impl std::str::FromStr for Grid<char> {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Grid::from_rows(s.lines().map(str::chars))
+    }
+}
+
+#[test]
+fn parse_rejects_ragged_rows() {
+    assert!("ab\ncd".parse::<Grid<char>>().is_ok());
+    assert!("ab\nc".parse::<Grid<char>>().is_err());
+}
+
+#[test]
+fn bounds_and_neighbors() {
+    let mut grid: Grid<char> = "abc\ndef".parse().unwrap();
+    assert_eq!((3, 2), (grid.width(), grid.height()));
+    assert_eq!(Some(&'f'), grid.get((2, 1).into()));
+    assert_eq!(None, grid.get((3, 0).into()));
+    assert_eq!(None, grid.get(Position { x: -1, y: 0 }));
+
+    let corner: Vec<_> = grid
+        .neighbors((0, 0).into(), Direction::orthogonal())
+        .map(|(_, c)| *c)
+        .collect();
+    assert_eq!(vec!['d', 'b'], corner);
+    assert_eq!(5, grid.neighbors((1, 0).into(), Direction::all()).count());
+
+    *grid.get_mut((1, 1).into()).unwrap() = 'x';
+    assert_eq!("abc\ndxf".parse::<Grid<char>>().unwrap(), grid);
+    assert_eq!(None, grid.get_mut((0, 2).into()));
+}
+
+#[test]
+fn lines_through_grid() {
+    let grid: Grid<char> = "abc\ndef".parse().unwrap();
+    let collect = |lines: Vec<Vec<&char>>| -> Vec<String> {
+        lines
+            .into_iter()
+            .map(|line| line.into_iter().collect())
+            .collect()
+    };
+    assert_eq!(
+        vec!["abc", "def"],
+        grid.rows()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["ad", "be", "cf"],
+        collect(grid.columns().map(Iterator::collect).collect())
+    );
+    assert_eq!(
+        vec!["ae", "bf", "c", "d"],
+        collect(grid.diagonals().map(Iterator::collect).collect())
+    );
+    assert_eq!(
+        vec!["a", "bd", "ce", "f"],
+        collect(grid.anti_diagonals().map(Iterator::collect).collect())
+    );
+}
+
+#[test]
+fn wrapping_cycles() {
+    let grid: Grid<char> = "abc\ndef".parse().unwrap();
+    assert_eq!(Position { x: 2, y: 1 }, grid.wrap(Position { x: -1, y: 3 }));
+    let cells = |cycles: Vec<Vec<Position>>| -> Vec<String> {
+        cycles
+            .into_iter()
+            .map(|cycle| cycle.into_iter().map(|p| grid.get(p).unwrap()).collect())
+            .collect()
+    };
+    assert_eq!(vec!["abc", "def"], cells(grid.cycles(Direction::Right)));
+    assert_eq!(vec!["ad", "be", "cf"], cells(grid.cycles(Direction::Up)));
+    // Diagonals on a 3x2 torus visit every cell before returning.
+    assert_eq!(vec!["aecdbf"], cells(grid.cycles(Direction::DownRight)));
+}
+
+#[test]
+fn transpose_and_rotate() {
+    let grid: Grid<char> = "abc\ndef".parse().unwrap();
+    assert_eq!(
+        "ad\nbe\ncf".parse::<Grid<char>>().unwrap(),
+        grid.transpose()
+    );
+    assert_eq!(
+        "da\neb\nfc".parse::<Grid<char>>().unwrap(),
+        grid.rotate_clockwise()
+    );
+    assert_eq!(
+        "cf\nbe\nad".parse::<Grid<char>>().unwrap(),
+        grid.rotate_counter_clockwise()
+    );
+    assert_eq!(grid, grid.rotate_clockwise().rotate_counter_clockwise());
+}
@@ -3,6 +3,7 @@ mod day02;
 mod day03;
 mod day04;
 mod day05;
+mod grid;
 
 fn main() {
     println!("Advent of Code 2024");